@@ -1,38 +1,23 @@
 use anyhow::Result;
 use bundler_core::{Bundler, Config};
+use bundler_resolver::Resolution;
 use clap::{Arg, Command};
-use tracing_subscriber;
+use std::path::{Path, PathBuf};
+
+/// Config files looked for in the working directory when `--config` isn't
+/// given, in order
+const DEFAULT_CONFIGS: [&str; 4] = [
+    "bundler.config.ts",
+    "bundler.config.mts",
+    "bundler.config.js",
+    "bundler.config.json",
+];
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::init();
+    tracing_subscriber::fmt::init();
 
-    let matches = Command::new("rust-bundler")
-        .version("0.1.0")
-        .about("A Webpack-like bundler written in Rust")
-        .subcommand_required(false)
-        .arg_required_else_help(false)
-        .subcommand(Command::new("init-types").about("Generate TypeScript type definitions"))
-        .arg(
-            Arg::new("entry")
-                .help("Entry point of the application")
-                .index(1),
-        )
-        .arg(
-            Arg::new("output")
-                .short('o')
-                .long("output")
-                .help("Output directory")
-                .default_value("dist"),
-        )
-        .arg(
-            Arg::new("config")
-                .short('c')
-                .long("config")
-                .help("Path to config file (.json, .ts, .mts)")
-                .value_name("FILE"),
-        )
-        .get_matches();
+    let matches = cli().get_matches();
     match matches.subcommand() {
         Some(("init-types", _)) => {
             #[cfg(feature = "generate-types")]
@@ -48,6 +33,15 @@ async fn main() -> Result<()> {
                 return Ok(());
             }
         }
+        Some(("resolve", resolve_matches)) => {
+            let config = load_config(matches.get_one::<String>("config"), Path::new("."))
+                .await?
+                .unwrap_or_default();
+            let specifier = resolve_matches.get_one::<String>("specifier").unwrap();
+            let from = resolve_matches.get_one::<String>("from").unwrap();
+
+            return resolve(&config, specifier, Path::new(from));
+        }
         _ => {
             // Original bundling logic
             let entry = matches.get_one::<String>("entry");
@@ -62,30 +56,10 @@ async fn main() -> Result<()> {
 
     let entry = matches.get_one::<String>("entry").unwrap();
     let output = matches.get_one::<String>("output").unwrap();
-    let config_path = matches.get_one::<String>("config");
-
-    let config = if let Some(config_path) = config_path {
-        Config::from_file(config_path).await?
-    } else {
-        // Try to find default config files
-        let default_configs = [
-            "bundler.config.ts",
-            "bundler.config.mts",
-            "bundler.config.js",
-            "bundler.config.json",
-        ];
-
-        let mut config = None;
-        for default_config in &default_configs {
-            if tokio::fs::metadata(default_config).await.is_ok() {
-                config = Some(Config::from_file(default_config).await?);
-                println!("📋 Using config file: {}", default_config);
-                break;
-            }
-        }
 
-        config.unwrap_or_else(|| Config::default().with_entry(entry).with_output_path(output))
-    };
+    let config = load_config(matches.get_one::<String>("config"), Path::new("."))
+        .await?
+        .unwrap_or_else(|| Config::default().with_entry(entry).with_output_path(output));
 
     let mut bundler = Bundler::new(config).await?;
     bundler.run().await?;
@@ -93,3 +67,130 @@ async fn main() -> Result<()> {
     println!("✅ Bundle completed successfully!");
     Ok(())
 }
+
+fn cli() -> Command {
+    Command::new("rust-bundler")
+        .version("0.1.0")
+        .about("A Webpack-like bundler written in Rust")
+        .subcommand_required(false)
+        .arg_required_else_help(false)
+        .subcommand(Command::new("init-types").about("Generate TypeScript type definitions"))
+        .subcommand(
+            Command::new("resolve")
+                .about("Resolve a module specifier the way the bundler does")
+                .arg(
+                    Arg::new("specifier")
+                        .help("Specifier to resolve, e.g. ./utils or lodash/fp")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .help("File the specifier is imported from")
+                        .value_name("FILE")
+                        .default_value("."),
+                ),
+        )
+        .arg(
+            Arg::new("entry")
+                .help("Entry point of the application")
+                .index(1),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .help("Output directory")
+                .default_value("dist"),
+        )
+        .arg(
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .help("Path to config file (.json, .ts, .mts)")
+                .value_name("FILE")
+                .global(true),
+        )
+}
+
+/// The config at `config_path`, or else the first of [`DEFAULT_CONFIGS`] in
+/// `dir`, if any. `build` and `resolve` both load it here, so they resolve
+/// modules the same way.
+async fn load_config(config_path: Option<&String>, dir: &Path) -> Result<Option<Config>> {
+    if let Some(config_path) = config_path {
+        return Ok(Some(Config::from_file(config_path).await?));
+    }
+
+    for default_config in DEFAULT_CONFIGS {
+        let path: PathBuf = dir.join(default_config);
+        if tokio::fs::metadata(&path).await.is_ok() {
+            let config = Config::from_file(&path.display().to_string()).await?;
+            println!("📋 Using config file: {}", default_config);
+            return Ok(Some(config));
+        }
+    }
+    Ok(None)
+}
+
+/// Print what `specifier` resolves to from `from`, followed by the candidates
+/// rejected on the way
+fn resolve(config: &Config, specifier: &str, from: &Path) -> Result<()> {
+    let (resolution, trace) = config.resolver().resolve_traced(specifier, from)?;
+
+    match resolution {
+        Resolution::File(path) => println!("{}", path.display()),
+        Resolution::Builtin(name) => println!("{} (Node.js built-in, external)", name),
+        Resolution::Empty => println!("(empty module)"),
+        Resolution::Virtual(id) => println!("{} (virtual module)", id.escape_debug()),
+    }
+
+    if !trace.is_empty() {
+        println!("Tried:");
+        for candidate in trace {
+            println!("  {} ({})", candidate.path.display(), candidate.kind);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_is_global() {
+        let matches = cli()
+            .try_get_matches_from(["rust-bundler", "resolve", "./a", "--config", "b.json"])
+            .unwrap();
+        assert_eq!(
+            matches.get_one::<String>("config").map(String::as_str),
+            Some("b.json")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_config_finds_default_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        assert!(load_config(None, dir).await.unwrap().is_none());
+
+        let mut config = serde_json::to_value(Config::default()).unwrap();
+        config["resolve"]["alias"] = serde_json::json!({ "@": "./src" });
+        std::fs::write(dir.join("bundler.config.json"), config.to_string()).unwrap();
+        std::fs::create_dir(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/a.js"), "").unwrap();
+
+        // `resolve` sees the aliases `build` would
+        let config = load_config(None, dir).await.unwrap().unwrap();
+        let resolution = config
+            .resolver()
+            .resolve_sync("@/a.js", &dir.join("index.js"))
+            .unwrap();
+        assert_eq!(
+            resolution,
+            Resolution::File(dir.canonicalize().unwrap().join("src/a.js"))
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Asset modules: files bundled as a URL or as their content rather than as
+//! code, such as images and fonts
+
+use crate::{BundlerError, Result, config::AssetType};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Where assets are emitted, relative to the output directory
+const ASSET_DIR: &str = "assets";
+
+/// Output filename of the asset at `path`, hashed so it can be cached forever
+pub(crate) fn filename(path: &Path, content: &[u8]) -> String {
+    let stem = path
+        .file_stem()
+        .map_or("asset".into(), |stem| stem.to_string_lossy());
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    format!(
+        "{}/{}.{}{}",
+        ASSET_DIR,
+        stem,
+        crate::hash::content_hash(content),
+        extension
+    )
+}
+
+/// Script of the asset module for the file at `path`. `asset/resource`
+/// emits the file into `emitted_files` and exports its URL under
+/// `public_path`. `Auto` must already be settled by size.
+pub(crate) fn module_source(
+    asset_type: AssetType,
+    path: &Path,
+    content: Vec<u8>,
+    public_path: &str,
+    emitted_files: &mut HashMap<String, Vec<u8>>,
+) -> Result<String> {
+    let export = match asset_type {
+        AssetType::Resource | AssetType::Auto => {
+            let filename = filename(path, &content);
+            let url = format!("{}{}", public_path, filename);
+            emitted_files.insert(filename, content);
+            url
+        }
+        AssetType::Inline => data_uri(path, &content),
+        AssetType::Source => String::from_utf8(content).map_err(|_| {
+            BundlerError::LoaderError(format!(
+                "{} is not UTF-8, so it can't be an `asset/source` module",
+                path.display()
+            ))
+        })?,
+    };
+
+    Ok(format!(
+        "module.exports = {};",
+        serde_json::to_string(&export).unwrap_or_default()
+    ))
+}
+
+/// `content` as a base64 `data:` URI, typed after `path`'s extension
+pub(crate) fn data_uri(path: &Path, content: &[u8]) -> String {
+    format!("data:{};base64,{}", mime_type(path), base64(content))
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        Some("ico") => "image/x-icon",
+        Some("bmp") => "image/bmp",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("eot") => "application/vnd.ms-fontobject",
+        Some("mp3") => "audio/mpeg",
+        Some("wav") => "audio/wav",
+        Some("ogg") => "audio/ogg",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("txt") => "text/plain",
+        Some("css") => "text/css",
+        Some("html") => "text/html",
+        Some("json") => "application/json",
+        Some("wasm") => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Standard, padded base64
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_sources() {
+        let path = Path::new("/app/images/dot.png");
+        let mut emitted = HashMap::new();
+
+        let source =
+            module_source(AssetType::Inline, path, b"hi!?".to_vec(), "/", &mut emitted).unwrap();
+        assert_eq!(
+            source,
+            r#"module.exports = "data:image/png;base64,aGkhPw==";"#
+        );
+
+        let source = module_source(
+            AssetType::Resource,
+            path,
+            vec![0xff],
+            "/static/",
+            &mut emitted,
+        )
+        .unwrap();
+        let filename = filename(path, &[0xff]);
+        assert_eq!(
+            source,
+            format!(r#"module.exports = "/static/{}";"#, filename)
+        );
+        assert_eq!(emitted[&filename], [0xff]);
+
+        let text = Path::new("notes.txt");
+        let source = module_source(
+            AssetType::Source,
+            text,
+            b"say \"hi\"".to_vec(),
+            "",
+            &mut emitted,
+        );
+        assert_eq!(source.unwrap(), r#"module.exports = "say \"hi\"";"#);
+        assert!(module_source(AssetType::Source, text, vec![0xff], "", &mut emitted).is_err());
+
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"a"), "YQ==");
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(base64(b"abc"), "YWJj");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_extensions_are_assets() {
+        use crate::{Config, compiler::Compiler};
+        use std::fs;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(
+            root.join("index.js"),
+            "import icon from './icon.gif';\nimport photo from './photo.jpg';\nimport { util } from './util.mjs';\n",
+        )
+        .unwrap();
+        fs::write(root.join("util.mjs"), "export const util = 1;\n").unwrap();
+        fs::write(root.join("icon.gif"), b"GIF89a").unwrap();
+        let photo = vec![0xd8; 10 * 1024];
+        fs::write(root.join("photo.jpg"), &photo).unwrap();
+
+        let mut config = Config {
+            entry: root.join("index.js").display().to_string(),
+            ..Config::default()
+        };
+        config.output.public_path = "/static/".to_string();
+        let result = Compiler::new(config).compile().await.unwrap();
+
+        let bundle = String::from_utf8_lossy(&result.assets["main.js"]);
+        assert!(bundle.contains(r#"module.exports = "data:image/gif;base64,R0lGODlh";"#));
+        let photo_filename = filename(Path::new("photo.jpg"), &photo);
+        assert!(bundle.contains(&format!(
+            r#"module.exports = "/static/{}";"#,
+            photo_filename
+        )));
+        assert_eq!(result.assets[&photo_filename], photo);
+        // `.mjs` and the like are scripts, not files of an unknown type
+        assert!(bundle.contains("exports.util"));
+    }
+}
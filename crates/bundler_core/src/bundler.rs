@@ -13,6 +13,11 @@ impl Bundler {
         Ok(Self { config, compiler })
     }
 
+    /// The compiler, to register plugins or virtual modules before `run`
+    pub fn compiler_mut(&mut self) -> &mut Compiler {
+        &mut self.compiler
+    }
+
     pub async fn run(&mut self) -> Result<()> {
         tracing::info!("Starting bundler...");
 
@@ -32,9 +37,16 @@ impl Bundler {
         Ok(())
     }
 
-    async fn write_assets(&self, assets: &std::collections::HashMap<String, String>) -> Result<()> {
+    async fn write_assets(
+        &self,
+        assets: &std::collections::HashMap<String, Vec<u8>>,
+    ) -> Result<()> {
         for (filename, content) in assets {
             let file_path = Path::new(&self.config.output.path).join(filename);
+            // Emitted files may go to subdirectories, e.g. `images/logo.png`
+            if let Some(parent) = file_path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
             tokio::fs::write(file_path, content).await?;
             tracing::info!("Generated: {}", filename);
         }
@@ -1,6 +1,6 @@
-use crate::{Module, Result};
+use crate::{Module, Result, dependency::DependencyType};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chunk {
@@ -9,13 +9,19 @@ pub struct Chunk {
     pub modules: Vec<String>, // module IDs
     pub entry_points: Vec<String>,
     pub chunk_type: ChunkType,
+    /// Id of the chunk whose runtime loads this one, for async chunks
+    pub parent: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ChunkType {
     Entry,
     Normal,
+    /// Loaded by its parent's runtime when an `import()` needs it
     Async,
+    /// Started with `new Worker(new URL(...))` or the like, so it runs in a
+    /// worker with a runtime of its own
+    Worker,
 }
 
 impl Chunk {
@@ -26,6 +32,7 @@ impl Chunk {
             modules: Vec::new(),
             entry_points: Vec::new(),
             chunk_type,
+            parent: None,
         }
     }
 
@@ -46,29 +53,288 @@ pub struct ChunkGenerator {
     chunks: Vec<Chunk>,
 }
 
+impl Default for ChunkGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ChunkGenerator {
     pub fn new() -> Self {
         Self { chunks: Vec::new() }
     }
 
     pub fn generate_chunks(&mut self, modules: &[Module]) -> Result<Vec<Chunk>> {
-        // For MVP, create a single chunk with all modules
+        let entries: Vec<&str> = modules
+            .iter()
+            .filter(|m| m.is_entry())
+            .map(|m| m.id.as_str())
+            .collect();
+
         let mut main_chunk = Chunk::new("main".to_string(), ChunkType::Entry);
+        for module_id in reachable(modules, &entries) {
+            main_chunk.add_module(module_id);
+        }
+        for entry in entries {
+            main_chunk.add_entry_point(entry.to_string());
+        }
+        main_chunk.name = Some("main".to_string());
+        let mut chunks = vec![main_chunk];
+
+        // Other chunks are named after the file of the module they start at
+        let mut names: HashSet<String> = HashSet::from(["main".to_string()]);
+        let mut unique_name = |module_id: &str| {
+            let stem = modules
+                .iter()
+                .find(|m| m.id == module_id)
+                .and_then(|m| m.path.file_stem())
+                .map_or("chunk".to_string(), |stem| {
+                    stem.to_string_lossy().into_owned()
+                });
+            let name = (1..)
+                .map(|n| {
+                    if n == 1 {
+                        stem.clone()
+                    } else {
+                        format!("{}-{}", stem, n)
+                    }
+                })
+                .find(|name| !names.contains(name))
+                .unwrap();
+            names.insert(name.clone());
+            name
+        };
 
+        // Each worker is the entry of a chunk of its own
         for module in modules {
-            main_chunk.add_module(module.id.clone());
-            if module.is_entry() {
-                main_chunk.add_entry_point(module.id.clone());
+            for dep in &module.dependencies {
+                let (DependencyType::Worker, Some(worker_id)) =
+                    (&dep.dependency_type, &dep.module_id)
+                else {
+                    continue;
+                };
+                if chunks.iter().any(|chunk| &chunk.id == worker_id) {
+                    continue;
+                }
+
+                let name = unique_name(worker_id);
+
+                let mut chunk = Chunk::new(worker_id.clone(), ChunkType::Worker);
+                for module_id in reachable(modules, &[worker_id.as_str()]) {
+                    chunk.add_module(module_id);
+                }
+                chunk.add_entry_point(worker_id.clone());
+                chunk.name = Some(name);
+                chunks.push(chunk);
             }
         }
 
-        main_chunk.name = Some("main".to_string());
-        self.chunks.push(main_chunk.clone());
+        // What a chunk loads with `import()` is split into async chunks of
+        // its own, without the modules the chunk already has
+        for root in chunks.clone() {
+            let mut targets: VecDeque<String> = dynamic_imports(modules, &root.modules).collect();
+            let mut split = HashSet::new();
+            while let Some(target) = targets.pop_front() {
+                if root.modules.contains(&target) || !split.insert(target.clone()) {
+                    continue;
+                }
+
+                let ids: Vec<String> = reachable(modules, &[target.as_str()])
+                    .into_iter()
+                    .filter(|id| !root.modules.contains(id))
+                    .collect();
+                targets.extend(dynamic_imports(modules, &ids));
+
+                let name = unique_name(&target);
+                let mut chunk = Chunk::new(name.clone(), ChunkType::Async);
+                for module_id in ids {
+                    chunk.add_module(module_id);
+                }
+                chunk.add_entry_point(target);
+                chunk.name = Some(name);
+                chunk.parent = Some(root.id.clone());
+                chunks.push(chunk);
+            }
+        }
 
-        Ok(vec![main_chunk])
+        self.chunks.extend(chunks.iter().cloned());
+        Ok(chunks)
     }
 
     pub fn get_chunks(&self) -> &[Chunk] {
         &self.chunks
     }
 }
+
+/// Ids of the modules `roots` import, directly or not, and the roots
+/// themselves, in the order of `modules`. Workers a module starts and
+/// modules it imports dynamically belong to chunks of their own, so they
+/// aren't followed.
+fn reachable(modules: &[Module], roots: &[&str]) -> Vec<String> {
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut stack = roots.to_vec();
+
+    while let Some(id) = stack.pop() {
+        if !seen.insert(id) {
+            continue;
+        }
+        let Some(module) = modules.iter().find(|m| m.id == id) else {
+            continue;
+        };
+        for dep in &module.dependencies {
+            if matches!(
+                dep.dependency_type,
+                DependencyType::Worker | DependencyType::DynamicImport
+            ) {
+                continue;
+            }
+            if let Some(dep_id) = &dep.module_id {
+                stack.push(dep_id);
+            }
+        }
+    }
+
+    modules
+        .iter()
+        .filter(|m| seen.contains(m.id.as_str()))
+        .map(|m| m.id.clone())
+        .collect()
+}
+
+/// Ids of the modules the modules `ids` load with `import()`
+fn dynamic_imports<'a>(
+    modules: &'a [Module],
+    ids: &'a [String],
+) -> impl Iterator<Item = String> + 'a {
+    modules
+        .iter()
+        .filter(|m| ids.contains(&m.id))
+        .flat_map(|m| &m.dependencies)
+        .filter(|dep| matches!(dep.dependency_type, DependencyType::DynamicImport))
+        .filter_map(|dep| dep.module_id.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, compiler::Compiler};
+    use std::fs;
+
+    #[tokio::test]
+    async fn test_worker_chunks() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::write(
+            root.join("index.js"),
+            "import { greet } from './shared.js';\nconst worker = new Worker(new URL('./worker.js', import.meta.url));\nnavigator.serviceWorker.register(new URL(\"./sw.js\", import.meta.url));\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("worker.js"),
+            "import { greet } from './shared.js';\nself.onmessage = () => greet();\n",
+        )
+        .unwrap();
+        fs::write(root.join("sw.js"), "self.skipWaiting();\n").unwrap();
+        fs::write(root.join("shared.js"), "export function greet() {}\n").unwrap();
+
+        let mut config = Config {
+            entry: root.join("index.js").display().to_string(),
+            ..Config::default()
+        };
+        config.output.public_path = "/static/".to_string();
+        let result = Compiler::new(config).compile().await.unwrap();
+
+        let id = |name: &str| root.join(name).display().to_string();
+        let chunk = |name: &str| {
+            result
+                .chunks
+                .iter()
+                .find(|chunk| chunk.name.as_deref() == Some(name))
+                .unwrap()
+        };
+        assert_eq!(
+            chunk("main").modules,
+            [id("shared.js"), format!("entry:{}", id("index.js"))]
+        );
+        assert_eq!(chunk("worker").modules, [id("shared.js"), id("worker.js")]);
+        assert_eq!(chunk("worker").entry_points, [id("worker.js")]);
+        assert_eq!(chunk("sw").modules, [id("sw.js")]);
+
+        let main = String::from_utf8_lossy(&result.assets["main.js"]);
+        assert!(
+            main.contains(
+                "new Worker(new URL(\"/static/worker.chunk.js\", __webpack_require__.b))"
+            )
+        );
+        assert!(main.contains(
+            "navigator.serviceWorker.register(new URL(\"/static/sw.chunk.js\", __webpack_require__.b))"
+        ));
+        let worker = String::from_utf8_lossy(&result.assets["worker.chunk.js"]);
+        assert!(!worker.contains("__webpack_require__.b ="));
+        assert!(worker.contains("self.onmessage"));
+        assert!(result.assets.contains_key("sw.chunk.js"));
+    }
+
+    #[tokio::test]
+    async fn test_async_chunks() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join("pages")).unwrap();
+        fs::write(
+            root.join("index.js"),
+            "import { layout } from './layout.js';\nconst pages = import.meta.glob('./pages/*.js');\nimport('./layout.js');\n",
+        )
+        .unwrap();
+        fs::write(root.join("layout.js"), "export const layout = 1;\n").unwrap();
+        fs::write(
+            root.join("pages/about.js"),
+            "import { layout } from '../layout.js';\nimport { format } from '../format.js';\nimport('./index.js');\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("pages/index.js"),
+            "import { format } from '../format.js';\n",
+        )
+        .unwrap();
+        fs::write(root.join("format.js"), "export function format() {}\n").unwrap();
+
+        let mut config = Config {
+            entry: root.join("index.js").display().to_string(),
+            ..Config::default()
+        };
+        config.output.public_path = "/static/".to_string();
+        let result = Compiler::new(config).compile().await.unwrap();
+
+        let id = |name: &str| root.join(name).display().to_string();
+        let chunk = |name: &str| {
+            result
+                .chunks
+                .iter()
+                .find(|chunk| chunk.name.as_deref() == Some(name))
+                .unwrap()
+        };
+        assert_eq!(
+            chunk("about").modules,
+            [id("format.js"), id("pages/about.js")]
+        );
+        assert_eq!(chunk("about").parent.as_deref(), Some("main"));
+        assert_eq!(
+            chunk("index").modules,
+            [id("format.js"), id("pages/index.js")]
+        );
+
+        let main = String::from_utf8_lossy(&result.assets["main.js"]);
+        assert!(main.contains("__webpack_require__.e = function(file) {"));
+        assert!(main.contains(&format!(
+            "\"./pages/about.js\": function() {{ return __webpack_require__.e(\"about.chunk.js\").then(function() {{ return __webpack_require__({:?}); }}); }}",
+            id("pages/about.js")
+        )));
+        // Modules in the entry's chunk already are just required
+        assert!(main.contains(&format!(
+            "Promise.resolve().then(function() {{ return __webpack_require__({:?}); }});",
+            id("layout.js")
+        )));
+        let about = String::from_utf8_lossy(&result.assets["about.chunk.js"]);
+        assert!(about.starts_with("(self.webpackChunk = self.webpackChunk || []).push({"));
+        assert!(about.contains("__webpack_require__.e(\"index.chunk.js\")"));
+    }
+}
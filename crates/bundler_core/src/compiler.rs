@@ -1,37 +1,86 @@
 use crate::{
     Result,
-    chunk::{Chunk, ChunkGenerator},
-    config::Config,
-    loader::LoaderRegistry,
-    module::{Module, ModuleGraph},
-    plugin::{PluginContext, PluginManager},
+    chunk::{Chunk, ChunkGenerator, ChunkType},
+    config::{AssetType, Config, Mode, Platform, WasmExports},
+    dependency::{Dependency, DependencyType},
+    loader::{Loader, LoaderContext, LoaderOutput, LoaderRegistry},
+    module::{Module, ModuleGraph, ModuleType},
+    plugin::{Plugin, PluginContext, PluginManager},
+    virtual_modules::{VirtualModuleFactory, VirtualModules},
 };
-use bundler_resolver::Resolver;
+use bundler_resolver::{Resolution, Resolver};
+use path_absolutize::Absolutize;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 pub struct Compiler {
     config: Config,
     loader_registry: LoaderRegistry,
     plugin_manager: PluginManager,
-    resolver: Resolver,
+    resolver: Arc<Resolver>,
+    virtual_modules: VirtualModules,
+    /// Browsers to lower syntax for, if any
+    browserslist: Option<String>,
+    /// What `import.meta.env` is in every module
+    env: serde_json::Value,
 }
 
 impl Compiler {
     pub fn new(config: Config) -> Self {
+        let resolver = Arc::new(config.resolver());
+        let root = config.root();
+        let browserslist =
+            crate::target::browserslist_query(config.target.as_deref(), &root, &config.mode);
+        let env = crate::import_meta::env(&config, &root, std::env::vars());
+
         Self {
             config,
             loader_registry: LoaderRegistry::new(),
             plugin_manager: PluginManager::new(),
-            resolver: Resolver::new(),
+            resolver,
+            virtual_modules: VirtualModules::new(),
+            browserslist,
+            env,
         }
     }
 
+    /// Make `loader` available to `module.rules` under `name`
+    pub fn register_loader(&mut self, name: impl Into<String>, loader: Box<dyn Loader>) {
+        self.loader_registry.register(name.into(), loader);
+    }
+
+    pub fn add_plugin(&mut self, plugin: Box<dyn Plugin>) {
+        self.plugin_manager.add_plugin(plugin);
+    }
+
+    /// Make `id` (e.g. `virtual:routes`) importable with `content` as its
+    /// source. It goes through loaders and chunking like a file would, with
+    /// the query it is imported with; relative imports in it are taken from
+    /// the project root, [`Config::root`].
+    pub fn add_virtual_module(&mut self, id: impl Into<String>, content: impl Into<String>) {
+        self.virtual_modules.add(id.into(), content.into());
+    }
+
+    /// Like [`Compiler::add_virtual_module`], with the content generated each
+    /// time the module is built
+    pub fn add_virtual_module_factory(
+        &mut self,
+        id: impl Into<String>,
+        factory: VirtualModuleFactory,
+    ) {
+        self.virtual_modules.add_factory(id.into(), factory);
+    }
+
+    /// Build the entry and everything it imports. An import that doesn't
+    /// resolve fails the compilation with
+    /// [`BundlerError::UnresolvedImport`](crate::BundlerError::UnresolvedImport).
     pub async fn compile(&mut self) -> Result<CompilationResult> {
         tracing::info!("Starting compilation...");
+        self.config.validate()?;
 
         // 1. Build module graph
-        let mut module_graph = self.build_module_graph().await?;
+        let module_graph = self.build_module_graph().await?;
 
         // 2. Check for circular dependencies
         module_graph.detect_circular_dependencies()?;
@@ -55,6 +104,7 @@ impl Compiler {
         Ok(CompilationResult {
             chunks,
             assets,
+            file_dependencies: file_dependencies(module_graph.get_modules()),
             modules: module_graph.get_modules().to_vec(),
         })
     }
@@ -63,9 +113,15 @@ impl Compiler {
         let mut module_graph = ModuleGraph::new();
         let mut processed = std::collections::HashSet::new();
 
-        // Start with entry module
+        // Start with entry module. Dependencies come back from the resolver
+        // already following the symlink policy; the entry has to match it.
         let entry_path = Path::new(&self.config.entry);
-        self.process_module(entry_path, &mut module_graph, &mut processed, true)
+        let entry_path = if self.config.resolve.symlinks {
+            entry_path.canonicalize()?
+        } else {
+            entry_path.absolutize()?.to_path_buf()
+        };
+        self.process_module(&entry_path, None, &mut module_graph, &mut processed, true)
             .await?;
 
         Ok(module_graph)
@@ -74,56 +130,490 @@ impl Compiler {
     async fn process_module(
         &self,
         path: &Path,
+        dependency: Option<&Dependency>,
         module_graph: &mut ModuleGraph,
-        processed: &mut std::collections::HashSet<PathBuf>,
+        processed: &mut std::collections::HashSet<String>,
         is_entry: bool,
-    ) -> Result<()> {
-        let absolute_path = path.canonicalize()?;
+    ) -> Result<String> {
+        let absolute_path = path.to_path_buf();
+        let query = dependency.and_then(|dep| dep.query.clone());
+        let fragment = dependency.and_then(|dep| dep.fragment.clone());
 
-        if processed.contains(&absolute_path) {
-            return Ok(());
+        // The same file requested with different queries is a different module
+        let resource = format!(
+            "{}{}{}",
+            absolute_path.display(),
+            query.as_deref().unwrap_or(""),
+            fragment.as_deref().unwrap_or("")
+        );
+        let module_id = if is_entry {
+            format!("entry:{}", resource)
+        } else {
+            resource
+        };
+
+        if processed.contains(&module_id) {
+            return Ok(module_id);
         }
 
-        processed.insert(absolute_path.clone());
+        processed.insert(module_id.clone());
 
-        // Read module content
-        let content = tokio::fs::read_to_string(&absolute_path).await?;
+        let source = read_source(&absolute_path).await?;
 
-        // Create module
-        let module_id = if is_entry {
-            format!("entry:{}", absolute_path.display())
-        } else {
-            absolute_path.display().to_string()
+        // Create module; its content is whatever its loaders make of the source
+        let module = Module::new(module_id, absolute_path.clone(), String::new())
+            .with_resource_query(query)
+            .with_resource_fragment(fragment);
+
+        self.build_module(module, source, &absolute_path, module_graph, processed)
+            .await
+    }
+
+    /// Load the virtual module `id` from the compiler's registry or, failing
+    /// that, from plugins. Like files, it is a different module for each
+    /// query `dependency` requests it with, and its loaders see the query.
+    async fn process_virtual_module(
+        &self,
+        id: &str,
+        dependency: &Dependency,
+        module_graph: &mut ModuleGraph,
+        processed: &mut std::collections::HashSet<String>,
+    ) -> Result<String> {
+        let module_id = format!(
+            "{}{}{}",
+            id,
+            dependency.query.as_deref().unwrap_or(""),
+            dependency.fragment.as_deref().unwrap_or("")
+        );
+        if !processed.insert(module_id.clone()) {
+            return Ok(module_id);
+        }
+
+        let content = match self.virtual_modules.load(id) {
+            Some(content) => content?,
+            None => self.plugin_manager.load_virtual(id).await?.ok_or_else(|| {
+                crate::BundlerError::ModuleNotFound {
+                    path: id.to_string(),
+                }
+            })?,
         };
 
-        let mut module = Module::new(module_id.clone(), absolute_path.clone(), content.clone());
+        // `virtual:theme.css` is CSS, but a plain `virtual:routes` is code
+        let mut module = Module::new(module_id, PathBuf::from(id), String::new())
+            .with_resource_query(dependency.query.clone())
+            .with_resource_fragment(dependency.fragment.clone());
+        if module.path.extension().is_none() {
+            module.module_type = ModuleType::JavaScript;
+        }
 
-        // Parse dependencies
-        let dependencies = self.parse_dependencies(&content, &absolute_path).await?;
+        // Imports inside a virtual module are relative to the project root
+        let context = self.config.root();
+        self.build_module(
+            module,
+            content.into_bytes(),
+            &context,
+            module_graph,
+            processed,
+        )
+        .await
+    }
 
-        for dep in dependencies {
-            module.add_dependency(dep.clone());
+    /// Run `source` through `module`'s loaders, resolve and process the
+    /// dependencies of the result, with relative requests taken from
+    /// `context`, then add the module to the graph
+    async fn build_module(
+        &self,
+        mut module: Module,
+        source: Vec<u8>,
+        context: &Path,
+        module_graph: &mut ModuleGraph,
+        processed: &mut std::collections::HashSet<String>,
+    ) -> Result<String> {
+        let module_id = module.id.clone();
+        // Errors point at the source as written, not at what loaders made of it
+        let written = String::from_utf8_lossy(&source).into_owned();
 
-            // Resolve dependency path
-            if let Ok(resolved_path) = self.resolver.resolve(&dep.request, &absolute_path).await {
-                module_graph.add_dependency(module_id.clone(), resolved_path.display().to_string());
+        // Loaders see the source first, so dependencies are taken from
+        // their output
+        self.run_loaders(&mut module, source).await?;
+        self.lower_syntax(&mut module)?;
+        // Glob imports expand into requests of the modules they match
+        if matches!(
+            module.module_type,
+            ModuleType::JavaScript | ModuleType::TypeScript
+        ) {
+            let dir = if context.is_dir() {
+                context
+            } else {
+                context.parent().unwrap_or(context)
+            };
+            module.content = crate::glob_import::expand(
+                &module.content,
+                &module.path,
+                dir,
+                &self.config.root(),
+            )?;
+        }
+
+        // Stylesheets reference other stylesheets and assets rather than
+        // scripts
+        let (dependencies, css_urls) = match module.module_type {
+            ModuleType::Css => {
+                let css = crate::css::parse_dependencies(&module.content);
+                (css.imports, css.urls)
+            }
+            // Asset modules only export a string
+            ModuleType::Asset => (Vec::new(), Vec::new()),
+            _ => (
+                self.parse_dependencies(&module.content, context).await?,
+                Vec::new(),
+            ),
+        };
+        // `import.meta.resolve()` only needs the path, not the module
+        let (resolves, dependencies): (Vec<_>, Vec<_>) = dependencies
+            .into_iter()
+            .partition(|dep| matches!(dep.dependency_type, DependencyType::ImportMeta));
 
+        for mut dep in dependencies {
+            let resolution = self
+                .resolve_dependency(&dep, &module, &written, context)
+                .await?;
+            let dependency_id = match resolution {
                 // Recursively process dependency
-                self.process_module(&resolved_path, module_graph, processed, false)
-                    .await?;
+                Resolution::File(resolved_path) => {
+                    Box::pin(self.process_module(
+                        &resolved_path,
+                        Some(&dep),
+                        module_graph,
+                        processed,
+                        false,
+                    ))
+                    .await?
+                }
+                Resolution::Virtual(id) => {
+                    Box::pin(self.process_virtual_module(&id, &dep, module_graph, processed))
+                        .await?
+                }
+                Resolution::Builtin(name) => self.add_generated_module(
+                    format!("external:{}", name),
+                    format!("module.exports = require({});", js_string(&name)),
+                    ModuleType::External,
+                    module_graph,
+                    processed,
+                ),
+                Resolution::Empty => self.add_generated_module(
+                    "empty".to_string(),
+                    String::new(),
+                    ModuleType::JavaScript,
+                    module_graph,
+                    processed,
+                ),
+            };
+
+            module_graph.add_dependency(module_id.clone(), dependency_id.clone());
+            dep.module_id = Some(dependency_id);
+            module.add_dependency(dep);
+        }
+        self.emit_css_assets(&mut module, css_urls, &written, context)
+            .await?;
+        self.resolve_import_meta(&mut module, resolves, &written, context)
+            .await?;
+
+        if matches!(module.module_type, ModuleType::Css) {
+            if crate::css_modules::is_css_module(&module.path) {
+                self.scope_css_module(&mut module, module_graph)?;
             }
+            self.compile_css(&mut module)?;
         }
 
         module_graph.add_module(module);
+        Ok(module_id)
+    }
+
+    /// Resolve `dep` of `module`, reporting where in `source`, the module as
+    /// written, it was requested if it can't be
+    async fn resolve_dependency(
+        &self,
+        dep: &Dependency,
+        module: &Module,
+        source: &str,
+        context: &Path,
+    ) -> Result<Resolution> {
+        self.resolver
+            .resolve(&dep.request, context)
+            .await
+            .map_err(|error| {
+                let (line, column) = dep.line_column(source, &module.content);
+                crate::BundlerError::UnresolvedImport {
+                    request: dep.request.clone(),
+                    file: module.path.display().to_string(),
+                    line,
+                    column,
+                    source: Box::new(error),
+                }
+            })
+    }
+
+    /// Emit the files `urls` of the stylesheet `module` refer to as assets
+    /// and point the `url()`s at the emitted files, under `public_path` like
+    /// the URLs asset modules export
+    async fn emit_css_assets(
+        &self,
+        module: &mut Module,
+        urls: Vec<Dependency>,
+        source: &str,
+        context: &Path,
+    ) -> Result<()> {
+        let mut replacements = Vec::new();
+
+        for dep in urls {
+            // Only files on disk have anything to emit
+            let Resolution::File(path) = self
+                .resolve_dependency(&dep, module, source, context)
+                .await?
+            else {
+                module.add_dependency(dep);
+                continue;
+            };
+
+            let content = read_source(&path).await?;
+            let filename = crate::asset::filename(&path, &content);
+            let url = format!(
+                "{}{}{}{}",
+                self.config.output.public_path,
+                filename,
+                dep.query.as_deref().unwrap_or(""),
+                dep.fragment.as_deref().unwrap_or("")
+            );
+            module.emitted_files.insert(filename, content);
+            if let Some(range) = dep.range.clone() {
+                replacements.push((range, url));
+            }
+            module.add_dependency(dep);
+        }
+
+        if !replacements.is_empty() {
+            module.content = crate::css::replace_ranges(&module.content, replacements);
+        }
         Ok(())
     }
 
-    async fn parse_dependencies(
+    /// Replace the `import.meta.resolve()` calls `resolves` of `module` with
+    /// what they resolve to: a `file:` URL for files
+    async fn resolve_import_meta(
         &self,
-        content: &str,
-        _path: &Path,
-    ) -> Result<Vec<crate::dependency::Dependency>> {
-        use crate::dependency::{Dependency, DependencyType};
+        module: &mut Module,
+        resolves: Vec<Dependency>,
+        source: &str,
+        context: &Path,
+    ) -> Result<()> {
+        let mut replacements = Vec::new();
+
+        for dep in resolves {
+            let resolved = match self
+                .resolve_dependency(&dep, module, source, context)
+                .await?
+            {
+                Resolution::File(path) => match self.config.platform {
+                    Platform::Node => crate::import_meta::file_url(&path),
+                    // Resolving a file doesn't emit it, so browsers have no
+                    // URL to load it from
+                    Platform::Web => {
+                        return Err(crate::BundlerError::ParseError {
+                            file: module.path.display().to_string(),
+                            message: format!(
+                                "import.meta.resolve('{}') has no URL on the web, since {} isn't emitted; import it instead",
+                                dep.request,
+                                path.display()
+                            ),
+                        });
+                    }
+                },
+                Resolution::Virtual(id) => id,
+                Resolution::Builtin(name) => format!("node:{}", name),
+                Resolution::Empty => dep.request.clone(),
+            };
+            if let Some(range) = dep.range.clone() {
+                replacements.push((range, js_string(&resolved)));
+            }
+            module.add_dependency(dep);
+        }
+
+        if !replacements.is_empty() {
+            module.content = crate::css::replace_ranges(&module.content, replacements);
+        }
+        Ok(())
+    }
+
+    /// Compile the stylesheet `module` for the configured browsers, minified
+    /// in production. Bundled `@import`s are dropped first, since the
+    /// stylesheets they name are modules of their own.
+    fn compile_css(&self, module: &mut Module) -> Result<()> {
+        let css = crate::css::strip_imports(&module.content);
+        module.content = crate::css::compile(
+            &css,
+            &module.path,
+            self.browserslist.as_deref(),
+            matches!(self.config.mode, Mode::Production),
+        )?;
+        Ok(())
+    }
+
+    /// Scope the class names of the CSS module `module` to it and record the
+    /// names it exports. Modules it composes classes from are already built.
+    fn scope_css_module(&self, module: &mut Module, module_graph: &ModuleGraph) -> Result<()> {
+        let root = self.config.root();
+        let path = module.path.strip_prefix(&root).unwrap_or(&module.path);
+        let scoped = crate::css_modules::scope(
+            &module.content,
+            path,
+            &self.config.css.modules.local_ident_name,
+            |request, name| {
+                let id = dependency_id(module, request);
+                module_graph
+                    .get_modules()
+                    .iter()
+                    .find(|m| m.id == id)
+                    .and_then(|m| m.css_classes.get(name))
+                    .cloned()
+            },
+        )?;
+
+        module.content = scoped.css;
+        module.css_classes = scoped.classes;
+        Ok(())
+    }
+
+    /// Run `source` through the loaders `module`'s matching rules select and
+    /// store the result in `module`
+    async fn run_loaders(&self, module: &mut Module, source: Vec<u8>) -> Result<()> {
+        let resource = module.path.display().to_string();
+        let loaders = self
+            .config
+            .module
+            .loaders_for(&resource, module.resource_query.as_deref())?;
+
+        let mut context = LoaderContext::new(
+            module.path.clone(),
+            module.resource_query.clone(),
+            self.config.mode.clone(),
+        )
+        .with_resolver(self.resolver.clone());
+        let output = self
+            .loader_registry
+            .run(&loaders, LoaderOutput::new(source), &mut context)
+            .await?;
+
+        module.build_dependencies = context.dependencies;
+        module.emitted_files = context.emitted_files;
+
+        // Files of types the bundler doesn't know, such as images, are
+        // assets unless a loader turned them into something else
+        let size = output.content.len();
+        let asset_type = self
+            .config
+            .module
+            .asset_type_for(&resource, module.resource_query.as_deref(), size)?
+            .or_else(|| {
+                let unknown = loaders.is_empty() && matches!(module.module_type, ModuleType::Asset);
+                unknown.then(|| AssetType::Auto.for_size(size, None))
+            });
+        if let Some(asset_type) = asset_type {
+            module.content = crate::asset::module_source(
+                asset_type,
+                &module.path,
+                output.content,
+                &self.config.output.public_path,
+                &mut module.emitted_files,
+            )?;
+            module.module_type = ModuleType::Asset;
+            return Ok(());
+        }
+
+        // WebAssembly no loader turned into something else is instantiated
+        // from an emitted copy. On Node.js it is read next to the bundle.
+        if output.module_type.is_none() && matches!(module.module_type, ModuleType::WebAssembly) {
+            let public_path = match self.config.platform {
+                Platform::Web => self.config.output.public_path.as_str(),
+                Platform::Node => "",
+            };
+            let path = module.path.clone();
+            let request = |name: &str| match self.config.wasm.imports.get(name) {
+                Some(request) => Some(self.config.root_request(request)),
+                None => self
+                    .resolver
+                    .resolve_sync(name, &path)
+                    .is_ok()
+                    .then(|| name.to_string()),
+            };
+            module.content = crate::wasm::module_source(
+                &path,
+                output.content,
+                self.config.wasm.exports,
+                public_path,
+                request,
+                &mut module.emitted_files,
+            )?;
+            return Ok(());
+        }
+
+        // Everything else past the loaders is text; binary files need a
+        // loader or an asset type that turns them into a module
+        module.content = String::from_utf8(output.content).map_err(|_| {
+            crate::BundlerError::LoaderError(format!(
+                "{} is not UTF-8 after its loaders ran; add a rule with a loader or an asset `type` for it",
+                module.path.display()
+            ))
+        })?;
+        if let Some(module_type) = output.module_type {
+            module.module_type = module_type;
+        }
+        Ok(())
+    }
+
+    /// Lower `module`'s syntax for the configured browsers
+    fn lower_syntax(&self, module: &mut Module) -> Result<()> {
+        let Some(query) = &self.browserslist else {
+            return Ok(());
+        };
+        // Polyfills must not be injected into the polyfills themselves
+        let is_core_js = module
+            .path
+            .components()
+            .any(|c| c.as_os_str() == "core-js" || c.as_os_str() == "core-js-pure");
+        if !matches!(module.module_type, ModuleType::JavaScript) || is_core_js {
+            return Ok(());
+        }
+
+        module.content = crate::transform::lower(
+            &module.content,
+            &module.path,
+            query,
+            self.config.core_js.as_deref(),
+        )?;
+        Ok(())
+    }
+
+    /// Add a module whose content is produced by the compiler rather than
+    /// read from disk, once per id
+    fn add_generated_module(
+        &self,
+        module_id: String,
+        content: String,
+        module_type: ModuleType,
+        module_graph: &mut ModuleGraph,
+        processed: &mut std::collections::HashSet<String>,
+    ) -> String {
+        if processed.insert(module_id.clone()) {
+            let mut module = Module::new(module_id.clone(), PathBuf::from(&module_id), content);
+            module.module_type = module_type;
+            module_graph.add_module(module);
+        }
+
+        module_id
+    }
+
+    async fn parse_dependencies(&self, content: &str, _path: &Path) -> Result<Vec<Dependency>> {
         use regex::Regex;
 
         let mut dependencies = Vec::new();
@@ -132,10 +622,21 @@ impl Compiler {
         let import_regex = Regex::new(r#"import\s+.*?\s+from\s+['"]([^'"]+)['"]"#).unwrap();
         for cap in import_regex.captures_iter(content) {
             if let Some(module_path) = cap.get(1) {
-                dependencies.push(Dependency::new(
-                    module_path.as_str().to_string(),
-                    DependencyType::Import,
-                ));
+                dependencies.push(
+                    Dependency::new(module_path.as_str().to_string(), DependencyType::Import)
+                        .with_range(module_path.start(), module_path.end()),
+                );
+            }
+        }
+
+        // Parse side-effect imports, e.g. injected polyfills
+        let side_effect_regex = Regex::new(r#"import\s*['"]([^'"]+)['"]"#).unwrap();
+        for cap in side_effect_regex.captures_iter(content) {
+            if let Some(module_path) = cap.get(1) {
+                dependencies.push(
+                    Dependency::new(module_path.as_str().to_string(), DependencyType::Import)
+                        .with_range(module_path.start(), module_path.end()),
+                );
             }
         }
 
@@ -143,13 +644,42 @@ impl Compiler {
         let require_regex = Regex::new(r#"require\(['"]([^'"]+)['"]\)"#).unwrap();
         for cap in require_regex.captures_iter(content) {
             if let Some(module_path) = cap.get(1) {
-                dependencies.push(Dependency::new(
-                    module_path.as_str().to_string(),
-                    DependencyType::Require,
-                ));
+                dependencies.push(
+                    Dependency::new(module_path.as_str().to_string(), DependencyType::Require)
+                        .with_range(module_path.start(), module_path.end()),
+                );
+            }
+        }
+
+        // Dynamic imports, which load async chunks
+        for cap in dynamic_import_regex().captures_iter(content) {
+            if let Some(module_path) = cap.get(1) {
+                dependencies.push(
+                    Dependency::new(
+                        module_path.as_str().to_string(),
+                        DependencyType::DynamicImport,
+                    )
+                    .with_range(module_path.start(), module_path.end()),
+                );
             }
         }
 
+        // Scripts started as workers, which become chunks of their own
+        for cap in worker_regex().captures_iter(content) {
+            if let Some(module_path) = cap.get(2) {
+                dependencies.push(
+                    Dependency::new(module_path.as_str().to_string(), DependencyType::Worker)
+                        .with_range(module_path.start(), module_path.end()),
+                );
+            }
+        }
+
+        dependencies.extend(crate::import_meta::parse_resolves(content));
+
+        // Modules run in the order they are imported, and stylesheets apply
+        // in the order modules run
+        dependencies.sort_by_key(|dep| dep.range.as_ref().map(|range| range.start));
+
         Ok(dependencies)
     }
 
@@ -157,22 +687,126 @@ impl Compiler {
         &self,
         chunks: &[Chunk],
         modules: &[Module],
-    ) -> Result<HashMap<String, String>> {
+    ) -> Result<HashMap<String, Vec<u8>>> {
         let mut assets = HashMap::new();
+        let filenames: HashMap<&str, String> = chunks
+            .iter()
+            .map(|chunk| (chunk.id.as_str(), self.chunk_filename(chunk)))
+            .collect();
 
         for chunk in chunks {
-            let asset_content = self.generate_chunk_content(chunk, modules).await?;
-            let filename = match &chunk.name {
-                Some(name) => format!("{}.js", name),
-                None => self.config.output.filename.clone(),
-            };
-            assets.insert(filename, asset_content);
+            let asset_content = self
+                .generate_chunk_content(chunk, chunks, modules, &filenames)
+                .await?;
+            assets.insert(
+                filenames[chunk.id.as_str()].clone(),
+                asset_content.into_bytes(),
+            );
+        }
+
+        // Outside development, each chunk's CSS is extracted into a file of
+        // its own. Workers have no document to style, and nothing would load
+        // the file of an async chunk, so it injects its CSS instead.
+        if !matches!(self.config.mode, Mode::Development) {
+            let chunks: Vec<&Chunk> = chunks
+                .iter()
+                .filter(|chunk| !matches!(chunk.chunk_type, ChunkType::Worker | ChunkType::Async))
+                .collect();
+            let stylesheets: Vec<Vec<&str>> = chunks
+                .iter()
+                .map(|chunk| {
+                    chunk
+                        .modules
+                        .iter()
+                        .filter(|id| {
+                            modules
+                                .iter()
+                                .any(|m| &m.id == *id && matches!(m.module_type, ModuleType::Css))
+                        })
+                        .map(String::as_str)
+                        .collect()
+                })
+                .collect();
+
+            for (first, second) in crate::css::order_conflicts(&stylesheets) {
+                tracing::warn!(
+                    "Conflicting order of {} and {}: chunks import them in different orders",
+                    first,
+                    second
+                );
+            }
+
+            for (chunk, ids) in chunks.iter().zip(&stylesheets) {
+                if ids.is_empty() {
+                    continue;
+                }
+                let css = ids
+                    .iter()
+                    .filter_map(|id| modules.iter().find(|m| m.id == *id))
+                    .map(|module| module.content.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let name = chunk.name.as_deref().unwrap_or(&chunk.id);
+                assets.insert(format!("{}.css", name), css.into_bytes());
+            }
+        }
+
+        // Files emitted by loaders
+        for module in modules {
+            for (filename, content) in &module.emitted_files {
+                assets.insert(filename.clone(), content.clone());
+            }
         }
 
         Ok(assets)
     }
 
-    async fn generate_chunk_content(&self, chunk: &Chunk, modules: &[Module]) -> Result<String> {
+    /// Output filename of `chunk`. Worker and async chunks are named after
+    /// `output.chunk_filename`.
+    fn chunk_filename(&self, chunk: &Chunk) -> String {
+        match (&chunk.chunk_type, &chunk.name) {
+            (ChunkType::Worker | ChunkType::Async, Some(name)) => {
+                self.config.output.chunk_filename.replace("[name]", name)
+            }
+            (_, Some(name)) => format!("{}.js", name),
+            (_, None) => self.config.output.filename.clone(),
+        }
+    }
+
+    /// Script of `chunk`. Async chunks only add their modules to the
+    /// runtime of their parent; other chunks have a runtime of their own.
+    /// `filenames` are the output filenames of `chunks` by id.
+    async fn generate_chunk_content(
+        &self,
+        chunk: &Chunk,
+        chunks: &[Chunk],
+        modules: &[Module],
+        filenames: &HashMap<&str, String>,
+    ) -> Result<String> {
+        let definitions = self
+            .module_definitions(chunk, chunks, modules, filenames)
+            .await?;
+        if matches!(chunk.chunk_type, ChunkType::Async) {
+            return Ok(match self.config.platform {
+                Platform::Web => format!(
+                    "(self.webpackChunk = self.webpackChunk || []).push({{{}}});",
+                    definitions
+                ),
+                Platform::Node => format!("module.exports = {{{}}};", definitions),
+            });
+        }
+
+        // Modules of the async chunks this chunk's runtime loads run on it too
+        let async_chunks: Vec<&Chunk> = chunks
+            .iter()
+            .filter(|other| other.parent.as_deref() == Some(chunk.id.as_str()))
+            .collect();
+        let runtime_modules: Vec<&Module> = std::iter::once(chunk)
+            .chain(async_chunks.iter().copied())
+            .flat_map(|chunk| &chunk.modules)
+            .filter_map(|id| modules.iter().find(|m| &m.id == id))
+            .collect();
+
         let mut bundle_content = String::new();
 
         // Generate module map
@@ -191,18 +825,142 @@ impl Compiler {
         bundle_content.push_str("    module.l = true;\n");
         bundle_content.push_str("    return module.exports;\n");
         bundle_content.push_str("  }\n");
+        bundle_content.push_str("  __webpack_require__.n = function(exports) {\n");
+        bundle_content
+            .push_str("    return exports && exports.__esModule ? exports.default : exports;\n");
+        bundle_content.push_str("  };\n");
+
+        let wasm_modules: Vec<&Module> = chunk
+            .modules
+            .iter()
+            .filter_map(|id| modules.iter().find(|m| &m.id == id))
+            .filter(|m| matches!(m.module_type, ModuleType::WebAssembly))
+            .collect();
+        if runtime_modules
+            .iter()
+            .any(|m| matches!(m.module_type, ModuleType::WebAssembly))
+        {
+            bundle_content.push_str(crate::wasm::runtime(self.config.platform));
+        }
 
-        // Add modules
-        bundle_content.push_str("  return __webpack_require__(__webpack_require__.s = ");
+        // `import.meta.url` is the URL of the running script, which workers
+        // and async chunks are found relative to
+        let uses_import_meta = runtime_modules.iter().any(|m| {
+            !matches!(
+                m.module_type,
+                ModuleType::Css | ModuleType::Asset | ModuleType::External
+            ) && m.content.contains("import.meta")
+        });
+        if uses_import_meta || !async_chunks.is_empty() {
+            let base = match (self.config.platform, &chunk.chunk_type) {
+                (Platform::Node, _) => "require(\"url\").pathToFileURL(__filename).href",
+                (Platform::Web, ChunkType::Worker) => "self.location.href",
+                (Platform::Web, _) => {
+                    "typeof document !== \"undefined\" && document.currentScript ? document.currentScript.src : self.location.href"
+                }
+            };
+            bundle_content.push_str(&format!("  __webpack_require__.b = {};\n", base));
+        }
+        if !async_chunks.is_empty() {
+            bundle_content.push_str(&self.chunk_loading_runtime());
+            if self.config.wasm.exports == WasmExports::Namespace {
+                bundle_content.push_str(&chunk_wasm_runtime(&async_chunks, modules, filenames));
+            }
+        }
 
-        if let Some(entry_point) = chunk.entry_points.first() {
-            bundle_content.push_str(&format!("'{}'", entry_point));
+        let entry_point = chunk
+            .entry_points
+            .first()
+            .map_or("0".to_string(), |entry_point| js_string(entry_point));
+
+        // WebAssembly exported as namespaces is instantiated up front, so
+        // the entry runs once every instance's exports are in place
+        if self.config.wasm.exports == WasmExports::Namespace && !wasm_modules.is_empty() {
+            let ids = wasm_modules
+                .iter()
+                .map(|m| js_string(&m.id))
+                .collect::<Vec<_>>()
+                .join(", ");
+            bundle_content.push_str(&format!(
+                "  [{}].forEach(function(moduleId) {{ __webpack_require__(moduleId); }});\n",
+                ids
+            ));
+            bundle_content
+                .push_str("  return Promise.all(__webpack_require__.w).then(function() {\n");
+            bundle_content.push_str(&format!(
+                "    return __webpack_require__(__webpack_require__.s = {});\n",
+                entry_point
+            ));
+            bundle_content.push_str("  });\n");
         } else {
-            bundle_content.push_str("0");
+            bundle_content.push_str(&format!(
+                "  return __webpack_require__(__webpack_require__.s = {});\n",
+                entry_point
+            ));
         }
+        bundle_content.push_str("})({");
+        bundle_content.push_str(&definitions);
+        bundle_content.push_str("});");
+        Ok(bundle_content)
+    }
 
-        bundle_content.push_str(");\n");
-        bundle_content.push_str("})([");
+    /// Runtime helper `__webpack_require__.e`, which loads the async chunk
+    /// in the file it is given and resolves once its modules can be
+    /// required
+    fn chunk_loading_runtime(&self) -> String {
+        match self.config.platform {
+            Platform::Web => format!(
+                r#"  var loadedChunks = {{}};
+  function installChunk(more) {{
+    for (var moduleId in more) modules[moduleId] = more[moduleId];
+  }}
+  var chunkQueue = self.webpackChunk = self.webpackChunk || [];
+  chunkQueue.forEach(installChunk);
+  chunkQueue.push = installChunk;
+  __webpack_require__.e = function(file) {{
+    if (!loadedChunks[file]) {{
+      var url = new URL({} + file, __webpack_require__.b).href;
+      loadedChunks[file] = new Promise(function(resolve, reject) {{
+        if (typeof document === "undefined") {{
+          importScripts(url);
+          return resolve();
+        }}
+        var script = document.createElement("script");
+        script.src = url;
+        script.onload = function() {{ resolve(); }};
+        script.onerror = function() {{
+          delete loadedChunks[file];
+          reject(new Error("Loading chunk " + file + " failed"));
+        }};
+        document.head.appendChild(script);
+      }});
+    }}
+    return loadedChunks[file];
+  }};
+"#,
+                js_string(&self.config.output.public_path)
+            ),
+            Platform::Node => r#"  __webpack_require__.e = function(file) {
+    return Promise.resolve().then(function() {
+      var more = require(require("path").join(__dirname, file));
+      for (var moduleId in more) modules[moduleId] = more[moduleId];
+    });
+  };
+"#
+            .to_string(),
+        }
+    }
+
+    /// The `"id": function(module, exports, __webpack_require__) {...}`
+    /// definitions of the modules of `chunk`
+    async fn module_definitions(
+        &self,
+        chunk: &Chunk,
+        chunks: &[Chunk],
+        modules: &[Module],
+        filenames: &HashMap<&str, String>,
+    ) -> Result<String> {
+        let mut bundle_content = String::new();
 
         for (i, module_id) in chunk.modules.iter().enumerate() {
             if i > 0 {
@@ -210,36 +968,120 @@ impl Compiler {
             }
 
             if let Some(module) = modules.iter().find(|m| &m.id == module_id) {
-                // Transform the module content
-                let transformed_content = self
-                    .transform_module_content(&module.content, module)
-                    .await?;
+                // Transform the module content. Externals are already written
+                // against the runtime's own `require`, and assets are
+                // generated as CommonJS.
+                let transformed_content = match module.module_type {
+                    ModuleType::External | ModuleType::Asset => module.content.clone(),
+                    ModuleType::Css => self.style_module_content(module, chunk),
+                    _ => {
+                        let content = self.rewrite_worker_urls(&module.content, module, filenames);
+                        let content =
+                            rewrite_dynamic_imports(&content, module, chunk, chunks, filenames);
+                        let content = crate::import_meta::rewrite(&content, &self.env);
+                        self.transform_module_content(&content, module).await?
+                    }
+                };
 
                 bundle_content.push_str(&format!(
-                    "/* {} */\nfunction(module, exports, __webpack_require__) {{\n{}\n}}",
-                    module_id, transformed_content
+                    "{}: function(module, exports, __webpack_require__) {{\n{}\n}}",
+                    js_string(module_id),
+                    transformed_content
                 ));
             }
         }
 
-        bundle_content.push_str("]);");
         Ok(bundle_content)
     }
 
+    /// Script standing in for the stylesheet `module` in `chunk`. In
+    /// development and in async chunks it applies the stylesheet, after the
+    /// ones it imports; otherwise the CSS is extracted. Workers have no
+    /// document to apply it to. CSS modules export their class names either
+    /// way.
+    fn style_module_content(&self, module: &Module, chunk: &Chunk) -> String {
+        let mut content = String::new();
+
+        let injected = match chunk.chunk_type {
+            ChunkType::Worker => false,
+            ChunkType::Async => true,
+            _ => matches!(self.config.mode, Mode::Development),
+        };
+        if injected {
+            for dep in &module.dependencies {
+                if let (DependencyType::CssImport, Some(id)) =
+                    (&dep.dependency_type, &dep.module_id)
+                {
+                    content.push_str(&format!("__webpack_require__({});\n", js_string(id)));
+                }
+            }
+            content.push_str(&crate::css::inject_style(&module.content));
+        }
+
+        if crate::css_modules::is_css_module(&module.path) {
+            let classes = serde_json::to_string(&module.css_classes).unwrap_or_default();
+            content.push_str(&format!("\nmodule.exports = {};", classes));
+        }
+        content
+    }
+
+    /// Point the URLs of the workers `module` starts at their chunks'
+    /// output files, under the public path in browsers
+    fn rewrite_worker_urls(
+        &self,
+        content: &str,
+        module: &Module,
+        filenames: &HashMap<&str, String>,
+    ) -> String {
+        worker_regex()
+            .replace_all(content, |caps: &regex::Captures| {
+                let id = dependency_id(module, &caps[2]);
+                let Some(filename) = filenames.get(id) else {
+                    return caps[0].to_string();
+                };
+                let url = match self.config.platform {
+                    Platform::Web => format!("{}{}", self.config.output.public_path, filename),
+                    Platform::Node => filename.clone(),
+                };
+                format!(
+                    "{}new URL({}, __webpack_require__.b)",
+                    &caps[1],
+                    js_string(&url)
+                )
+            })
+            .into_owned()
+    }
+
     async fn transform_module_content(&self, content: &str, module: &Module) -> Result<String> {
+        // Requests are rewritten to the ids of the modules they resolved to
         // Transform ES6 imports/exports to CommonJS for the runtime
         let mut transformed = content.to_string();
 
+        // Transform namespace imports
+        let namespace_import_regex =
+            regex::Regex::new(r#"import\s+\*\s+as\s+(\w+)\s+from\s+['"]([^'"]+)['"]"#).unwrap();
+        transformed = namespace_import_regex
+            .replace_all(&transformed, |caps: &regex::Captures| {
+                format!(
+                    "const {} = __webpack_require__({});",
+                    &caps[1],
+                    js_string(dependency_id(module, &caps[2]))
+                )
+            })
+            .to_string();
+
         // Transform imports
         let import_regex =
             regex::Regex::new(r#"import\s+\{([^}]+)\}\s+from\s+['"]([^'"]+)['"]"#).unwrap();
         transformed = import_regex
             .replace_all(&transformed, |caps: &regex::Captures| {
-                let imports = &caps[1];
+                // `a as b` is `a: b` when destructuring
+                let imports = caps[1].replace(" as ", ": ");
                 let module_path = &caps[2];
                 format!(
-                    "const {{ {} }} = __webpack_require__('{}');",
-                    imports, module_path
+                    "const {{ {} }} = __webpack_require__({});",
+                    imports,
+                    js_string(dependency_id(module, module_path))
                 )
             })
             .to_string();
@@ -252,30 +1094,345 @@ impl Compiler {
                 let var_name = &caps[1];
                 let module_path = &caps[2];
                 format!(
-                    "const {} = __webpack_require__('{}');",
-                    var_name, module_path
+                    "const {} = __webpack_require__.n(__webpack_require__({}));",
+                    var_name,
+                    js_string(dependency_id(module, module_path))
+                )
+            })
+            .to_string();
+
+        // Transform side-effect imports
+        let side_effect_regex = regex::Regex::new(r#"import\s*['"]([^'"]+)['"];?"#).unwrap();
+        transformed = side_effect_regex
+            .replace_all(&transformed, |caps: &regex::Captures| {
+                format!(
+                    "__webpack_require__({});",
+                    js_string(dependency_id(module, &caps[1]))
                 )
             })
             .to_string();
 
-        // Transform exports
+        // Transform requires
+        let require_regex = regex::Regex::new(r#"require\(['"]([^'"]+)['"]\)"#).unwrap();
+        transformed = require_regex
+            .replace_all(&transformed, |caps: &regex::Captures| {
+                format!(
+                    "__webpack_require__({})",
+                    js_string(dependency_id(module, &caps[1]))
+                )
+            })
+            .to_string();
+
+        // Transform default exports. A module with named exports too keeps
+        // them, so its default export is `exports.default`, which default
+        // imports read through `__webpack_require__.n`.
+        let default_export_regex = regex::Regex::new(r"export\s+default\s+").unwrap();
         let export_regex = regex::Regex::new(r"export\s+function\s+(\w+)").unwrap();
+        let export_binding_regex =
+            regex::Regex::new(r"export\s+(const|let|var)\s+(\w+)\s*=").unwrap();
+        if default_export_regex.is_match(&transformed) {
+            if export_regex.is_match(&transformed) || export_binding_regex.is_match(&transformed) {
+                // On the first line, so that source maps still line up
+                transformed = format!(
+                    "exports.__esModule = true; {}",
+                    default_export_regex.replace_all(&transformed, "exports.default = ")
+                );
+            } else {
+                transformed = default_export_regex
+                    .replace_all(&transformed, "module.exports = ")
+                    .to_string();
+            }
+        }
+
+        // Transform exports. Declarations are hoisted, so the function can
+        // be exported before the statement that declares it.
         transformed = export_regex
             .replace_all(&transformed, |caps: &regex::Captures| {
                 let func_name = &caps[1];
                 format!(
-                    "function {}\nexports.{} = {};",
+                    "exports.{} = {};\nfunction {}",
                     func_name, func_name, func_name
                 )
             })
             .to_string();
 
+        // Transform exported bindings
+        transformed = export_binding_regex
+            .replace_all(&transformed, "$1 $2 = exports.$2 =")
+            .to_string();
+
         Ok(transformed)
     }
 }
 
 pub struct CompilationResult {
     pub chunks: Vec<Chunk>,
-    pub assets: HashMap<String, String>,
+    pub assets: HashMap<String, Vec<u8>>,
     pub modules: Vec<Module>,
+    /// Every file the build read, sorted: modules on disk and the files
+    /// their loaders declared. A change to any of them calls for a rebuild.
+    pub file_dependencies: Vec<PathBuf>,
+}
+
+/// Files on disk `modules` were built from
+fn file_dependencies(modules: &[Module]) -> Vec<PathBuf> {
+    let files: std::collections::BTreeSet<PathBuf> = modules
+        .iter()
+        .filter(|module| bundler_resolver::fs::is_file(&module.path))
+        .map(|module| module.path.clone())
+        .chain(
+            modules
+                .iter()
+                .flat_map(|module| module.build_dependencies.iter().cloned()),
+        )
+        .collect();
+    files.into_iter().collect()
+}
+
+/// Matches the `new URL(request, import.meta.url)` a worker is started with,
+/// capturing what precedes the URL and the request
+fn worker_regex() -> regex::Regex {
+    regex::Regex::new(
+        r#"((?:new\s+(?:Shared)?Worker|navigator\.serviceWorker\.register)\(\s*)new\s+URL\(\s*['"]([^'"]+)['"]\s*,\s*import\.meta\.url\s*\)"#,
+    )
+    .unwrap()
+}
+
+fn dynamic_import_regex() -> regex::Regex {
+    regex::Regex::new(r#"\bimport\(\s*['"]([^'"]+)['"]\s*\)"#).unwrap()
+}
+
+/// Replace the `import()`s of `module`, in `chunk`, with loading the async
+/// chunk of the imported module, if it isn't in the chunk's runtime
+/// already, then requiring the module
+fn rewrite_dynamic_imports(
+    content: &str,
+    module: &Module,
+    chunk: &Chunk,
+    chunks: &[Chunk],
+    filenames: &HashMap<&str, String>,
+) -> String {
+    let root = chunk.parent.as_deref().unwrap_or(&chunk.id);
+
+    dynamic_import_regex()
+        .replace_all(content, |caps: &regex::Captures| {
+            let id = dependency_id(module, &caps[1]);
+            let async_chunk = chunks.iter().find(|other| {
+                other.parent.as_deref() == Some(root)
+                    && other.entry_points.first().map(String::as_str) == Some(id)
+            });
+            let loaded = match async_chunk {
+                Some(async_chunk) => format!(
+                    "__webpack_require__.e({})",
+                    js_string(&filenames[async_chunk.id.as_str()])
+                ),
+                None => "Promise.resolve()".to_string(),
+            };
+            format!(
+                "{}.then(function() {{ return __webpack_require__({}); }})",
+                loaded,
+                js_string(id)
+            )
+        })
+        .into_owned()
+}
+
+/// Runtime wrapping `__webpack_require__.e` so that the WebAssembly
+/// modules of the async chunk it loads are instantiated before it resolves,
+/// like the entry's are before the entry runs. Empty if none of
+/// `async_chunks` has any.
+fn chunk_wasm_runtime(
+    async_chunks: &[&Chunk],
+    modules: &[Module],
+    filenames: &HashMap<&str, String>,
+) -> String {
+    let entries: Vec<String> = async_chunks
+        .iter()
+        .filter_map(|chunk| {
+            let ids: Vec<String> = chunk
+                .modules
+                .iter()
+                .filter_map(|id| modules.iter().find(|m| &m.id == id))
+                .filter(|m| matches!(m.module_type, ModuleType::WebAssembly))
+                .map(|m| js_string(&m.id))
+                .collect();
+            (!ids.is_empty()).then(|| {
+                format!(
+                    "{}: [{}]",
+                    js_string(&filenames[chunk.id.as_str()]),
+                    ids.join(", ")
+                )
+            })
+        })
+        .collect();
+    if entries.is_empty() {
+        return String::new();
+    }
+
+    format!(
+        r#"  var chunkWasm = {{{}}};
+  var loadChunk = __webpack_require__.e;
+  __webpack_require__.e = function(file) {{
+    return loadChunk(file).then(function() {{
+      return Promise.all((chunkWasm[file] || []).map(function(moduleId) {{
+        __webpack_require__(moduleId);
+        return installedModules[moduleId].w;
+      }}));
+    }});
+  }};
+"#,
+        entries.join(", ")
+    )
+}
+
+/// Id of the module `request` resolved to from `module`, falling back to the
+/// request itself
+fn dependency_id<'a>(module: &'a Module, request: &'a str) -> &'a str {
+    module
+        .dependencies
+        .iter()
+        .find(|dep| dep.request == request)
+        .and_then(|dep| dep.module_id.as_deref())
+        .unwrap_or(request)
+}
+
+/// Read the source of the file at `path`. Plug'n'Play packages may live
+/// inside zip archives, which only the resolver knows how to read.
+async fn read_source(path: &Path) -> Result<Vec<u8>> {
+    if bundler_resolver::fs::is_special_path(path) {
+        Ok(bundler_resolver::fs::read(path)?)
+    } else {
+        Ok(tokio::fs::read(path).await?)
+    }
+}
+
+/// Quote `value` as a JavaScript string literal
+fn js_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| format!("{:?}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[tokio::test]
+    async fn test_default_and_named_exports() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(
+            root.join("index.js"),
+            "import greet from './greet.js';\nimport { name } from './greet.js';\nimport config from './config.json';\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("greet.js"),
+            "export const name = 'app';\nexport default function greet() {}\n",
+        )
+        .unwrap();
+        fs::write(root.join("config.json"), r#"{ "port": 80 }"#).unwrap();
+
+        let config = Config {
+            entry: root.join("index.js").display().to_string(),
+            ..Config::default()
+        };
+        let result = Compiler::new(config).compile().await.unwrap();
+
+        let bundle = String::from_utf8_lossy(&result.assets["main.js"]);
+        // The default export doesn't replace the named ones
+        assert!(bundle.contains(
+            "exports.__esModule = true; const name = exports.name = 'app';\nexports.default = function greet() {}"
+        ));
+        assert!(bundle.contains("const port = exports.port = 80;"));
+        assert!(bundle.contains("exports.default = { port };"));
+        assert!(bundle.contains("const greet = __webpack_require__.n(__webpack_require__("));
+        assert!(bundle.contains("const config = __webpack_require__.n(__webpack_require__("));
+    }
+
+    #[tokio::test]
+    async fn test_css_urls_under_public_path() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("index.js"), "import './style.css';\n").unwrap();
+        fs::write(
+            root.join("style.css"),
+            "body { background: url(./bg.png); }\n",
+        )
+        .unwrap();
+        fs::write(root.join("bg.png"), b"\x89PNG").unwrap();
+
+        let mut config = Config {
+            entry: root.join("index.js").display().to_string(),
+            mode: Mode::Production,
+            ..Config::default()
+        };
+        config.output.public_path = "/static/".to_string();
+        let result = Compiler::new(config).compile().await.unwrap();
+
+        let filename = crate::asset::filename(Path::new("bg.png"), b"\x89PNG");
+        assert!(result.assets.contains_key(&filename));
+        let css = String::from_utf8_lossy(&result.assets["main.css"]);
+        assert!(css.contains(&format!("url(/static/{})", filename)));
+    }
+
+    #[tokio::test]
+    async fn test_file_dependencies() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::write(root.join("index.js"), "import './style.scss';\n").unwrap();
+        fs::write(
+            root.join("style.scss"),
+            "@use 'vars';\nbody { color: vars.$text; }\n",
+        )
+        .unwrap();
+        fs::write(root.join("_vars.scss"), "$text: #333;\n").unwrap();
+
+        let config = Config {
+            entry: root.join("index.js").display().to_string(),
+            ..Config::default()
+        };
+        let result = Compiler::new(config).compile().await.unwrap();
+
+        // The partial is only known to the Sass loader
+        assert_eq!(
+            result.file_dependencies,
+            [
+                root.join("_vars.scss"),
+                root.join("index.js"),
+                root.join("style.scss")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unresolved_import_location() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        // The interface is gone by the time dependencies are parsed
+        fs::write(
+            root.join("index.ts"),
+            "interface Options {\n  debug: boolean;\n}\nimport './missing.js';\n",
+        )
+        .unwrap();
+
+        let config = Config {
+            entry: root.join("index.ts").display().to_string(),
+            ..Config::default()
+        };
+        let Err(error) = Compiler::new(config).compile().await else {
+            panic!("compiled with a missing import");
+        };
+        match error {
+            crate::BundlerError::UnresolvedImport {
+                request,
+                line,
+                column,
+                ..
+            } => {
+                assert_eq!(request, "./missing.js");
+                assert_eq!((line, column), (4, 9));
+            }
+            other => panic!("expected an unresolved import, got {}", other),
+        }
+    }
 }
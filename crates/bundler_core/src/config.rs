@@ -1,41 +1,461 @@
 use crate::Result;
+use bundler_resolver::Resolver;
+use path_absolutize::Absolutize;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Config {
+    /// Project root, which relative alias targets are taken from. Defaults
+    /// to the directory of the config file, or to the working directory.
+    #[serde(default)]
+    pub context: Option<String>,
     pub entry: String,
     pub output: OutputConfig,
     pub module: ModuleConfig,
     pub resolve: ResolveConfig,
     pub plugins: Vec<String>,
     pub mode: Mode,
+    #[serde(default)]
+    pub platform: Platform,
+    /// ES version (`"es2017"`) or browserslist query (`"chrome 49, safari
+    /// 10"`) to lower syntax for. Defaults to the project's `browserslist` in
+    /// `package.json` or `.browserslistrc`; without one, syntax is left as
+    /// written.
+    #[serde(default)]
+    pub target: Option<String>,
+    /// core-js version, e.g. `"3.38"`, to import polyfills from for the
+    /// features modules use that `target` lacks
+    #[serde(default, alias = "core_js")]
+    pub core_js: Option<String>,
+    #[serde(default)]
+    pub css: CssConfig,
+    #[serde(default)]
+    pub wasm: WasmConfig,
+    /// Prefix of the variables from `.env` files and the environment that
+    /// `import.meta.env` exposes. Others may be secrets, so they aren't.
+    #[serde(default = "default_env_prefix", alias = "env_prefix")]
+    pub env_prefix: String,
+}
+
+fn default_env_prefix() -> String {
+    "VITE_".to_string()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct OutputConfig {
     pub path: String,
     pub filename: String,
+    #[serde(alias = "chunk_filename")]
     pub chunk_filename: String,
+    /// Prefix of the URLs asset modules export, e.g. `/static/`; empty for
+    /// URLs relative to the page
+    #[serde(default, alias = "public_path")]
+    pub public_path: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ModuleConfig {
     pub rules: Vec<Rule>,
 }
 
+impl ModuleConfig {
+    /// Loaders to run on `resource`, in the order the matching rules list
+    /// them. Every matching top-level rule contributes; within a `one_of` only
+    /// the first matching rule does.
+    pub fn loaders_for(&self, resource: &str, query: Option<&str>) -> Result<Vec<UseEntry>> {
+        Ok(self
+            .matching_rules(resource, query)?
+            .into_iter()
+            .flat_map(|rule| rule.use_loader.iter().cloned())
+            .collect())
+    }
+
+    /// The asset type of `resource`, a file of `size` bytes, if a matching
+    /// rule gives one. The last such rule wins.
+    pub fn asset_type_for(
+        &self,
+        resource: &str,
+        query: Option<&str>,
+        size: usize,
+    ) -> Result<Option<AssetType>> {
+        Ok(self
+            .matching_rules(resource, query)?
+            .into_iter()
+            .rev()
+            .find_map(|rule| {
+                rule.asset_type
+                    .map(|asset_type| asset_type.for_size(size, rule.max_inline_size))
+            }))
+    }
+
+    /// Rules that apply to `resource`, outer rules before their `one_of`
+    fn matching_rules(&self, resource: &str, query: Option<&str>) -> Result<Vec<&Rule>> {
+        let mut rules = Vec::new();
+        for rule in &self.rules {
+            rule.collect_matching(resource, query, &mut rules)?;
+        }
+        Ok(rules)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Rule {
-    pub test: String, // regex pattern
-    pub use_loader: String,
+    /// Regex the resource path must match; empty matches every module
+    #[serde(default)]
+    pub test: String,
+    /// Loaders to run on matching modules, executed last to first. Accepts a
+    /// single loader or a list; empty for rules that only group `one_of`
+    /// alternatives.
+    #[serde(
+        rename = "use",
+        alias = "useLoader",
+        alias = "use_loader",
+        default,
+        deserialize_with = "one_or_many"
+    )]
+    pub use_loader: Vec<UseEntry>,
+    /// Regex the resource path must also match
+    #[serde(default)]
+    pub include: Option<String>,
     pub exclude: Option<String>,
+    /// Regex matched against the resource query, e.g. `^\?raw$`
+    #[serde(default, alias = "resource_query")]
+    pub resource_query: Option<String>,
+    /// Alternatives of which only the first matching one applies
+    #[serde(default, alias = "one_of")]
+    pub one_of: Vec<Rule>,
+    /// Bundle matching modules as assets, once their loaders ran
+    #[serde(rename = "type", alias = "asset_type", default)]
+    pub asset_type: Option<AssetType>,
+    /// Largest file, in bytes, that `asset` inlines; defaults to 8 KiB
+    #[serde(default, alias = "max_inline_size")]
+    pub max_inline_size: Option<usize>,
+}
+
+/// How an asset module exports its file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AssetType {
+    /// Emit the file and export its public URL
+    #[serde(rename = "asset/resource")]
+    Resource,
+    /// Export the file as a base64 `data:` URI
+    #[serde(rename = "asset/inline")]
+    Inline,
+    /// Export the file's content as a string
+    #[serde(rename = "asset/source")]
+    Source,
+    /// `Inline` for files up to `max_inline_size`, `Resource` for larger ones
+    #[serde(rename = "asset")]
+    Auto,
+}
+
+impl AssetType {
+    /// The type a file of `size` bytes ends up with, `Auto` deciding by
+    /// `max_inline_size`
+    pub fn for_size(self, size: usize, max_inline_size: Option<usize>) -> Self {
+        match self {
+            Self::Auto if size <= max_inline_size.unwrap_or(DEFAULT_MAX_INLINE_SIZE) => {
+                Self::Inline
+            }
+            Self::Auto => Self::Resource,
+            asset_type => asset_type,
+        }
+    }
+}
+
+const DEFAULT_MAX_INLINE_SIZE: usize = 8 * 1024;
+
+impl Rule {
+    /// Whether this rule applies to the module at `resource`, requested with
+    /// the resource query `query`
+    pub fn matches(&self, resource: &str, query: Option<&str>) -> Result<bool> {
+        if !self.test.is_empty() && !compile_condition(&self.test)?.is_match(resource) {
+            return Ok(false);
+        }
+
+        if let Some(include) = &self.include
+            && !compile_condition(include)?.is_match(resource)
+        {
+            return Ok(false);
+        }
+
+        if let Some(exclude) = &self.exclude
+            && compile_condition(exclude)?.is_match(resource)
+        {
+            return Ok(false);
+        }
+
+        if let Some(resource_query) = &self.resource_query {
+            return Ok(compile_condition(resource_query)?.is_match(query.unwrap_or("")));
+        }
+
+        Ok(true)
+    }
+
+    /// Append this rule and the `one_of` alternative that applies to
+    /// `resource` if it matches, returning whether it did
+    fn collect_matching<'a>(
+        &'a self,
+        resource: &str,
+        query: Option<&str>,
+        rules: &mut Vec<&'a Rule>,
+    ) -> Result<bool> {
+        if !self.matches(resource, query)? {
+            return Ok(false);
+        }
+
+        rules.push(self);
+        for rule in &self.one_of {
+            if rule.collect_matching(resource, query, rules)? {
+                break;
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// A loader in a rule's `use` list, written either as its name or as
+/// `{ "loader": name, "options": {...} }`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "UseEntryDef")]
+pub struct UseEntry {
+    pub loader: String,
+    /// Passed as-is to the loader; `null` when not given
+    #[serde(default)]
+    pub options: serde_json::Value,
+}
+
+impl UseEntry {
+    pub fn new(loader: impl Into<String>) -> Self {
+        Self {
+            loader: loader.into(),
+            options: serde_json::Value::Null,
+        }
+    }
+
+    pub fn with_options(mut self, options: serde_json::Value) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum UseEntryDef {
+    Name(String),
+    Full {
+        loader: String,
+        #[serde(default)]
+        options: serde_json::Value,
+    },
+}
+
+impl From<UseEntryDef> for UseEntry {
+    fn from(def: UseEntryDef) -> Self {
+        match def {
+            UseEntryDef::Name(loader) => UseEntry::new(loader),
+            UseEntryDef::Full { loader, options } => UseEntry { loader, options },
+        }
+    }
+}
+
+fn one_or_many<'de, D>(deserializer: D) -> std::result::Result<Vec<UseEntry>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(UseEntry),
+        Many(Vec<UseEntry>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(entry) => vec![entry],
+        OneOrMany::Many(entries) => entries,
+    })
+}
+
+fn compile_condition(pattern: &str) -> Result<regex::Regex> {
+    regex::Regex::new(pattern).map_err(|e| {
+        crate::BundlerError::ConfigError(format!("Invalid rule pattern '{}': {}", pattern, e))
+    })
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ResolveConfig {
     pub extensions: Vec<String>,
     pub alias: HashMap<String, String>,
+    /// Resolve symlinks to their real path, see `Resolver::with_symlinks`
+    #[serde(default = "default_symlinks")]
+    pub symlinks: bool,
+    /// Replacements for Node.js built-ins when bundling for the web, keyed by
+    /// module name without the `node:` prefix
+    #[serde(default)]
+    pub fallback: HashMap<String, FallbackTarget>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FallbackTarget {
+    /// Module to use instead, e.g. `"path-browserify"`
+    Module(String),
+    /// `false` replaces the built-in with an empty module; `true` is
+    /// rejected when the config is loaded
+    Disabled(bool),
+}
+
+impl FallbackTarget {
+    /// What the resolver does instead of the built-in, if anything. `true`
+    /// isn't a fallback, so the built-in stays unresolved.
+    pub fn to_fallback(&self) -> Option<bundler_resolver::Fallback> {
+        match self {
+            FallbackTarget::Module(request) => {
+                Some(bundler_resolver::Fallback::Module(request.clone()))
+            }
+            FallbackTarget::Disabled(false) => Some(bundler_resolver::Fallback::Empty),
+            FallbackTarget::Disabled(true) => None,
+        }
+    }
+}
+
+fn default_symlinks() -> bool {
+    true
+}
+
+/// How JSX is compiled, given as the `jsx` option of the `javascript` and
+/// `typescript` loaders. Unset fields fall back to `tsconfig.json` for
+/// TypeScript files, then to the automatic runtime with React.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct JsxConfig {
+    pub runtime: Option<JsxRuntime>,
+    /// Package providing `jsx-runtime` for the automatic runtime, e.g.
+    /// `"preact"`; defaults to `"react"`
+    #[serde(alias = "import_source")]
+    pub import_source: Option<String>,
+    /// Element factory for the classic runtime; defaults to
+    /// `React.createElement`
+    pub pragma: Option<String>,
+    /// Fragment component for the classic runtime; defaults to
+    /// `React.Fragment`
+    #[serde(alias = "pragma_frag")]
+    pub pragma_frag: Option<String>,
+    /// Add `__source`/`__self` debug info; defaults to on in development mode
+    pub development: Option<bool>,
+}
+
+impl JsxConfig {
+    /// Fill fields unset in `self` from `base`
+    pub fn or(self, base: Self) -> Self {
+        Self {
+            runtime: self.runtime.or(base.runtime),
+            import_source: self.import_source.or(base.import_source),
+            pragma: self.pragma.or(base.pragma),
+            pragma_frag: self.pragma_frag.or(base.pragma_frag),
+            development: self.development.or(base.development),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JsxRuntime {
+    /// `React.createElement` calls, or whatever `pragma` names
+    Classic,
+    /// Calls into `<import_source>/jsx-runtime`, imported automatically
+    Automatic,
+}
+
+/// How `@decorator` syntax is compiled, given as the `decorators` option of
+/// the `javascript` and `typescript` loaders. TypeScript files otherwise
+/// follow `experimentalDecorators` and `emitDecoratorMetadata` in
+/// `tsconfig.json`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DecoratorConfig {
+    pub version: Option<DecoratorVersion>,
+    /// Emit `design:type`/`design:paramtypes` metadata for
+    /// `reflect-metadata`; legacy decorators only
+    #[serde(alias = "emit_metadata")]
+    pub emit_metadata: Option<bool>,
+}
+
+impl DecoratorConfig {
+    /// Fill fields unset in `self` from `base`
+    pub fn or(self, base: Self) -> Self {
+        Self {
+            version: self.version.or(base.version),
+            emit_metadata: self.emit_metadata.or(base.emit_metadata),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DecoratorVersion {
+    /// TypeScript's `experimentalDecorators`
+    Legacy,
+    /// The TC39 proposal as standardized in 2023 and implemented by
+    /// TypeScript 5 (the 2022-03 spec)
+    Standard,
+}
+
+/// How stylesheets are built
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CssConfig {
+    pub modules: CssModulesConfig,
+}
+
+/// How `*.module.css` files scope their class names
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CssModulesConfig {
+    /// Pattern of scoped class names, made of `[name]` (the file name without
+    /// `.module.css`), `[local]` (the class name in the source) and `[hash]`
+    /// of the file's path from the project root, which can be shortened like
+    /// `[hash:5]`
+    #[serde(alias = "local_ident_name")]
+    pub local_ident_name: String,
+}
+
+impl Default for CssModulesConfig {
+    fn default() -> Self {
+        Self {
+            local_ident_name: "[name]_[local]_[hash:5]".to_string(),
+        }
+    }
+}
+
+/// How `.wasm` modules are instantiated
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WasmConfig {
+    pub exports: WasmExports,
+    /// Module whose exports provide each import namespace that isn't a
+    /// request of its own, e.g. `{ "env": "./wasm-env.js" }`. Relative
+    /// requests are taken from the project root.
+    pub imports: HashMap<String, String>,
+}
+
+/// What importing a `.wasm` module gives
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WasmExports {
+    /// The instance's exports, as in the ES module integration proposal. The
+    /// bundle instantiates its WebAssembly modules before running the entry,
+    /// which then sees their exports as if they were loaded synchronously.
+    #[default]
+    Namespace,
+    /// A function taking extra imports and returning a promise of the
+    /// instance's exports
+    Init,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,35 +464,111 @@ pub enum Mode {
     Production,
 }
 
+/// Environment the bundle runs in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Platform {
+    /// Browsers; Node.js built-ins need a `resolve.fallback`
+    #[default]
+    Web,
+    /// Node.js; built-ins are left to `require` at runtime
+    Node,
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
+            context: None,
             entry: "./src/index.js".to_string(),
             output: OutputConfig {
                 path: "./dist".to_string(),
                 filename: "bundle.js".to_string(),
                 chunk_filename: "[name].chunk.js".to_string(),
+                public_path: String::new(),
             },
             module: ModuleConfig {
                 rules: vec![
                     Rule {
-                        test: r"\.js$".to_string(),
-                        use_loader: "javascript".to_string(),
+                        test: r"\.jsx?$".to_string(),
+                        use_loader: vec![UseEntry::new("javascript")],
+                        include: None,
                         exclude: Some(r"node_modules".to_string()),
+                        resource_query: None,
+                        one_of: vec![],
+                        asset_type: None,
+                        max_inline_size: None,
                     },
                     Rule {
-                        test: r"\.ts$".to_string(),
-                        use_loader: "typescript".to_string(),
+                        test: r"\.tsx?$".to_string(),
+                        use_loader: vec![UseEntry::new("typescript")],
+                        include: None,
                         exclude: Some(r"node_modules".to_string()),
+                        resource_query: None,
+                        one_of: vec![],
+                        asset_type: None,
+                        max_inline_size: None,
+                    },
+                    Rule {
+                        test: r"\.json[c5]?$".to_string(),
+                        use_loader: vec![UseEntry::new("json")],
+                        include: None,
+                        exclude: None,
+                        resource_query: None,
+                        one_of: vec![],
+                        asset_type: None,
+                        max_inline_size: None,
+                    },
+                    Rule {
+                        test: r"\.ya?ml$".to_string(),
+                        use_loader: vec![UseEntry::new("yaml")],
+                        include: None,
+                        exclude: None,
+                        resource_query: None,
+                        one_of: vec![],
+                        asset_type: None,
+                        max_inline_size: None,
+                    },
+                    Rule {
+                        test: r"\.toml$".to_string(),
+                        use_loader: vec![UseEntry::new("toml")],
+                        include: None,
+                        exclude: None,
+                        resource_query: None,
+                        one_of: vec![],
+                        asset_type: None,
+                        max_inline_size: None,
+                    },
+                    Rule {
+                        test: r"\.s[ac]ss$".to_string(),
+                        use_loader: vec![UseEntry::new("sass")],
+                        include: None,
+                        exclude: None,
+                        resource_query: None,
+                        one_of: vec![],
+                        asset_type: None,
+                        max_inline_size: None,
                     },
                 ],
             },
             resolve: ResolveConfig {
-                extensions: vec![".js".to_string(), ".ts".to_string(), ".json".to_string()],
+                extensions: vec![
+                    ".js".to_string(),
+                    ".jsx".to_string(),
+                    ".ts".to_string(),
+                    ".tsx".to_string(),
+                    ".json".to_string(),
+                ],
                 alias: HashMap::new(),
+                symlinks: default_symlinks(),
+                fallback: HashMap::new(),
             },
             plugins: vec![],
             mode: Mode::Development,
+            platform: Platform::default(),
+            target: None,
+            core_js: None,
+            css: CssConfig::default(),
+            wasm: WasmConfig::default(),
+            env_prefix: default_env_prefix(),
         }
     }
 }
@@ -82,16 +578,43 @@ impl Config {
         let content = tokio::fs::read_to_string(path).await?;
 
         // Check file extension to determine format
-        let config = if path.ends_with(".ts") || path.ends_with(".mts") {
+        let mut config: Self = if path.ends_with(".ts") || path.ends_with(".mts") {
             Self::parse_typescript_config(&content).await?
         } else {
             serde_json::from_str(&content)
                 .map_err(|e| crate::BundlerError::ConfigError(e.to_string()))?
         };
 
+        config.validate()?;
+
+        // A relative `context` is relative to the config file too
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        let context = dir.join(config.context.as_deref().unwrap_or(""));
+        config.context = Some(context.absolutize()?.display().to_string());
+
         Ok(config)
     }
 
+    /// Reject settings that deserialize but mean nothing
+    pub(crate) fn validate(&self) -> Result<()> {
+        // Every variable would start with it, secrets included
+        if self.env_prefix.is_empty() {
+            return Err(crate::BundlerError::ConfigError(
+                "envPrefix: an empty prefix would expose every environment variable to import.meta.env"
+                    .to_string(),
+            ));
+        }
+        for (name, target) in &self.resolve.fallback {
+            if let FallbackTarget::Disabled(true) = target {
+                return Err(crate::BundlerError::ConfigError(format!(
+                    "resolve.fallback.{}: expected a module name or false, found true",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+
     async fn parse_typescript_config(content: &str) -> Result<Self> {
         // Simple TypeScript config parser
         // In a real implementation, you'd use a proper TS parser
@@ -164,10 +687,319 @@ impl Config {
         Ok(json_content)
     }
 
-    // ... existing code ...
+    /// A resolver set up from `resolve` and `platform`, resolving exactly like
+    /// the compiler does
+    pub fn resolver(&self) -> Resolver {
+        let fallback = self
+            .resolve
+            .fallback
+            .iter()
+            .filter_map(|(name, target)| Some((name.clone(), target.to_fallback()?)))
+            .collect();
+
+        // Relative alias targets are relative to the project root, not to
+        // each importer
+        let alias = self
+            .resolve
+            .alias
+            .iter()
+            .map(|(key, target)| (key.clone(), self.root_request(target)))
+            .collect();
+
+        // Packages can point bundles for browsers and Node.js at different
+        // files through `exports` conditions
+        let platform = match self.platform {
+            Platform::Web => "browser",
+            Platform::Node => "node",
+        };
+        let conditions = ["import", "module", platform].map(str::to_string).to_vec();
+
+        Resolver::new()
+            .with_extensions(self.resolve.extensions.clone())
+            .with_symlinks(self.resolve.symlinks)
+            .with_externalize_builtins(self.platform == Platform::Node)
+            .with_fallback(fallback)
+            .with_alias(alias)
+            .with_conditions(conditions)
+    }
+
+    /// `request` with a relative path made absolute from the project root,
+    /// for requests in the config that aren't made by any one module
+    pub(crate) fn root_request(&self, request: &str) -> String {
+        if !(request.starts_with("./") || request.starts_with("../")) {
+            return request.to_string();
+        }
+        Path::new(request)
+            .absolutize_from(self.root())
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|_| request.to_string())
+    }
+
+    /// The project root: `context`, or the working directory
+    pub fn root(&self) -> PathBuf {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        match &self.context {
+            Some(context) => Path::new(context)
+                .absolutize_from(&cwd)
+                .map(|path| path.into_owned())
+                .unwrap_or(cwd),
+            None => cwd,
+        }
+    }
+
+    pub fn with_entry(mut self, entry: impl Into<String>) -> Self {
+        self.entry = entry.into();
+        self
+    }
+
+    pub fn with_output_path(mut self, path: impl Into<String>) -> Self {
+        self.output.path = path.into();
+        self
+    }
 }
 
 // Add helper function for TypeScript configs
 pub fn define_config(config: Config) -> Config {
     config
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(test: &str, use_loader: &str) -> Rule {
+        Rule {
+            test: test.to_string(),
+            use_loader: use_loader.split_whitespace().map(UseEntry::new).collect(),
+            include: None,
+            exclude: None,
+            resource_query: None,
+            one_of: vec![],
+            asset_type: None,
+            max_inline_size: None,
+        }
+    }
+
+    #[test]
+    fn test_loaders_for_rules_and_one_of() {
+        let module = ModuleConfig {
+            rules: vec![
+                rule(r"\.ts$", "typescript"),
+                Rule {
+                    include: Some("src/".to_string()),
+                    one_of: vec![
+                        Rule {
+                            resource_query: Some(r"^\?raw$".to_string()),
+                            ..rule("", "raw")
+                        },
+                        rule(r"\.ts$", "banner"),
+                        rule("", "javascript"),
+                    ],
+                    ..rule("", "")
+                },
+            ],
+        };
+
+        let names = |resource: &str, query: Option<&str>| -> Vec<String> {
+            module
+                .loaders_for(resource, query)
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.loader)
+                .collect()
+        };
+
+        assert_eq!(names("/app/src/a.ts", None), ["typescript", "banner"]);
+        assert_eq!(names("/app/src/a.ts", Some("?raw")), ["typescript", "raw"]);
+        assert_eq!(names("/app/lib/a.ts", None), ["typescript"]);
+        assert!(names("/app/lib/a.js", None).is_empty());
+    }
+
+    #[test]
+    fn test_asset_type_for() {
+        let module = ModuleConfig {
+            rules: vec![
+                Rule {
+                    asset_type: Some(AssetType::Auto),
+                    max_inline_size: Some(100),
+                    ..rule(r"\.(png|svg)$", "")
+                },
+                Rule {
+                    asset_type: Some(AssetType::Source),
+                    resource_query: Some(r"^\?raw$".to_string()),
+                    ..rule("", "")
+                },
+            ],
+        };
+
+        let asset_type =
+            |resource, query, size| module.asset_type_for(resource, query, size).unwrap();
+        assert_eq!(asset_type("a.png", None, 100), Some(AssetType::Inline));
+        assert_eq!(asset_type("a.png", None, 101), Some(AssetType::Resource));
+        assert_eq!(
+            asset_type("a.svg", Some("?raw"), 101),
+            Some(AssetType::Source)
+        );
+        assert_eq!(asset_type("a.js", None, 1), None);
+
+        let rule: Rule =
+            serde_json::from_value(serde_json::json!({ "type": "asset/inline" })).unwrap();
+        assert_eq!(rule.asset_type, Some(AssetType::Inline));
+    }
+
+    #[test]
+    fn test_rule_use_forms() {
+        let rules: Vec<Rule> = serde_json::from_value(serde_json::json!([
+            { "test": "a", "use_loader": "javascript" },
+            { "test": "b", "use": "javascript" },
+            {
+                "test": "c",
+                "use": ["style", { "loader": "css", "options": { "modules": true } }]
+            },
+            { "test": "d", "useLoader": "javascript" },
+        ]))
+        .unwrap();
+
+        assert_eq!(rules[0].use_loader, [UseEntry::new("javascript")]);
+        assert_eq!(rules[1].use_loader, [UseEntry::new("javascript")]);
+        assert_eq!(rules[3].use_loader, [UseEntry::new("javascript")]);
+        assert_eq!(
+            rules[2].use_loader,
+            [
+                UseEntry::new("style"),
+                UseEntry::new("css").with_options(serde_json::json!({ "modules": true })),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_camel_case_keys() {
+        let rule: Rule = serde_json::from_value(serde_json::json!({
+            "test": "\\.svg$",
+            "resourceQuery": "^\\?raw$",
+            "oneOf": [{ "use": "raw" }],
+            "maxInlineSize": 1024,
+        }))
+        .unwrap();
+        assert_eq!(rule.resource_query.as_deref(), Some(r"^\?raw$"));
+        assert_eq!(rule.one_of[0].use_loader, [UseEntry::new("raw")]);
+        assert_eq!(rule.max_inline_size, Some(1024));
+
+        let output: OutputConfig = serde_json::from_value(serde_json::json!({
+            "path": "dist",
+            "filename": "bundle.js",
+            "chunk_filename": "[name].chunk.js",
+            "publicPath": "/static/",
+        }))
+        .unwrap();
+        assert_eq!(output.public_path, "/static/");
+
+        let jsx: JsxConfig = serde_json::from_value(serde_json::json!({
+            "importSource": "preact",
+            "pragma_frag": "Fragment",
+        }))
+        .unwrap();
+        assert_eq!(jsx.import_source.as_deref(), Some("preact"));
+        assert_eq!(jsx.pragma_frag.as_deref(), Some("Fragment"));
+
+        let decorators: DecoratorConfig = serde_json::from_value(serde_json::json!({
+            "version": "Legacy",
+            "emitMetadata": true,
+        }))
+        .unwrap();
+        assert_eq!(decorators.emit_metadata, Some(true));
+
+        let css: CssConfig = serde_json::from_value(serde_json::json!({
+            "modules": { "localIdentName": "[local]" },
+        }))
+        .unwrap();
+        assert_eq!(css.modules.local_ident_name, "[local]");
+
+        // Written out the way it is read
+        let config = serde_json::to_value(Config::default()).unwrap();
+        assert!(config["output"]["chunkFilename"].is_string());
+        assert!(config["envPrefix"].is_string());
+        let config: Config = serde_json::from_value(config).unwrap();
+        assert_eq!(config.output.chunk_filename, "[name].chunk.js");
+    }
+
+    #[tokio::test]
+    async fn test_empty_env_prefix_is_rejected() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("bundler.config.json");
+        let mut config = serde_json::to_value(Config::default()).unwrap();
+
+        config["envPrefix"] = "APP_".into();
+        std::fs::write(&config_path, config.to_string()).unwrap();
+        let loaded = Config::from_file(&config_path.display().to_string())
+            .await
+            .unwrap();
+        assert_eq!(loaded.env_prefix, "APP_");
+
+        config["envPrefix"] = "".into();
+        std::fs::write(&config_path, config.to_string()).unwrap();
+        let err = Config::from_file(&config_path.display().to_string())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("envPrefix"));
+
+        // Nor does a config built in code get to compile with one
+        let config = Config {
+            env_prefix: String::new(),
+            ..Config::default()
+        };
+        assert!(matches!(
+            crate::compiler::Compiler::new(config).compile().await,
+            Err(crate::BundlerError::ConfigError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fallback_true_is_rejected() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("bundler.config.json");
+        let mut config = serde_json::to_value(Config::default()).unwrap();
+        config["resolve"]["fallback"] = serde_json::json!({ "path": false, "fs": true });
+        std::fs::write(&config_path, config.to_string()).unwrap();
+
+        let err = Config::from_file(&config_path.display().to_string())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("resolve.fallback.fs"));
+
+        // Built without loading the file, `true` still doesn't stub `fs` out
+        let mut config = Config::default();
+        config
+            .resolve
+            .fallback
+            .insert("fs".to_string(), FallbackTarget::Disabled(true));
+        assert!(
+            config
+                .resolver()
+                .resolve_sync("fs", Path::new("/index.js"))
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_alias_relative_to_config_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("sub/src")).unwrap();
+        std::fs::write(root.join("sub/src/a.js"), "").unwrap();
+        let config_path = root.join("sub/bundler.config.json");
+        let mut config = serde_json::to_value(Config::default()).unwrap();
+        config["resolve"]["alias"] = serde_json::json!({ "@": "./src" });
+        std::fs::write(&config_path, config.to_string()).unwrap();
+
+        let config = Config::from_file(&config_path.display().to_string())
+            .await
+            .unwrap();
+        assert_eq!(config.root(), root.join("sub"));
+        let resolution = config
+            .resolver()
+            .resolve_sync("@/a", &root.join("index.js"))
+            .unwrap();
+        assert_eq!(resolution.path(), Some(root.join("sub/src/a.js").as_path()));
+    }
+}
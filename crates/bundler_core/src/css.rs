@@ -0,0 +1,371 @@
+//! CSS in the module graph
+//!
+//! `@import`s are edges of the graph, like a script's imports. `url()`s point
+//! at files that are emitted as assets next to the bundle. In production the
+//! CSS of each chunk is extracted into its own `.css` file; in development
+//! every module injects a `<style>` when it runs instead. Either way it is
+//! compiled with lightningcss for the target browsers first.
+
+use crate::{
+    BundlerError, Result,
+    dependency::{Dependency, DependencyType, Range},
+};
+use lightningcss::stylesheet::{
+    MinifyOptions, ParserFlags, ParserOptions, PrinterOptions, StyleSheet,
+};
+use lightningcss::targets::{Browsers, Targets};
+use regex::Regex;
+use std::path::Path;
+
+/// The references of a stylesheet, in source order
+pub(crate) struct CssDependencies {
+    /// Stylesheets pulled in with `@import` or composed from
+    pub imports: Vec<Dependency>,
+    /// Files referred to with `url()`
+    pub urls: Vec<Dependency>,
+}
+
+fn import_regex() -> Regex {
+    Regex::new(r#"@import\s+(?:url\(\s*)?(?:"([^"]*)"|'([^']*)'|([^'"\s;)]+))\s*\)?([^;]*);?"#)
+        .unwrap()
+}
+
+fn url_regex() -> Regex {
+    Regex::new(r#"\burl\(\s*(?:"([^"]*)"|'([^']*)'|([^'"\s)]*))\s*\)"#).unwrap()
+}
+
+/// The quoted or bare reference a match of one of the regexes captured
+fn reference<'a>(caps: &regex::Captures<'a>) -> regex::Match<'a> {
+    caps.get(1)
+        .or_else(|| caps.get(2))
+        .or_else(|| caps.get(3))
+        .unwrap()
+}
+
+/// Find the `@import`s and `url()`s of `content`, skipping ones inside
+/// comments and ones that point outside the project
+pub(crate) fn parse_dependencies(content: &str) -> CssDependencies {
+    let masked = mask_comments(content);
+    let mut imports = Vec::new();
+    let mut statements = Vec::new();
+
+    for caps in import_regex().captures_iter(&masked) {
+        let url = reference(&caps);
+        statements.push(caps.get(0).unwrap().range());
+        let Some(request) = request(url.as_str()) else {
+            continue;
+        };
+        if !caps[4].trim().is_empty() {
+            tracing::warn!(
+                "Bundling '@import \"{}\" {}' without its conditions",
+                url.as_str(),
+                caps[4].trim()
+            );
+        }
+        imports.push(
+            Dependency::new(request, DependencyType::CssImport).with_range(url.start(), url.end()),
+        );
+    }
+
+    // Classes a CSS module composes from another one need that module
+    let composes_regex =
+        Regex::new(r#"composes\s*:[^;{}]*?\sfrom\s+(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    for caps in composes_regex.captures_iter(&masked) {
+        let url = reference(&caps);
+        if let Some(request) = request(url.as_str()) {
+            imports.push(
+                Dependency::new(request, DependencyType::CssImport)
+                    .with_range(url.start(), url.end()),
+            );
+        }
+    }
+
+    let urls = url_regex()
+        .captures_iter(&masked)
+        .filter(|caps| {
+            let start = caps.get(0).unwrap().start();
+            !statements
+                .iter()
+                .any(|statement| statement.contains(&start))
+        })
+        .filter_map(|caps| {
+            let url = reference(&caps);
+            request(url.as_str()).map(|request| {
+                Dependency::new(request, DependencyType::CssUrl).with_range(url.start(), url.end())
+            })
+        })
+        .collect();
+
+    CssDependencies { imports, urls }
+}
+
+/// The request a CSS reference stands for, or `None` if it isn't bundled.
+/// Like in browsers, `a.png` is relative to the stylesheet; `~pkg/a.png`
+/// looks up a package.
+pub(crate) fn request(url: &str) -> Option<String> {
+    let url = url.trim();
+    let is_external = url.is_empty()
+        || url.starts_with('#')
+        || url.starts_with('/')
+        || Regex::new(r"^[a-zA-Z][a-zA-Z\d+.-]*:")
+            .unwrap()
+            .is_match(url);
+    if is_external {
+        return None;
+    }
+
+    Some(match url.strip_prefix('~') {
+        Some(package) => package.to_string(),
+        None if url.starts_with("./") || url.starts_with("../") => url.to_string(),
+        None => format!("./{}", url),
+    })
+}
+
+/// Replace comments with spaces, keeping every other byte where it was
+pub(crate) fn mask_comments(content: &str) -> String {
+    Regex::new(r"(?s)/\*.*?\*/")
+        .unwrap()
+        .replace_all(content, |caps: &regex::Captures| " ".repeat(caps[0].len()))
+        .into_owned()
+}
+
+/// `content` without the `@import`s that were bundled in its place
+pub(crate) fn strip_imports(content: &str) -> String {
+    let masked = mask_comments(content);
+    let mut stripped = String::with_capacity(content.len());
+    let mut last = 0;
+
+    for caps in import_regex().captures_iter(&masked) {
+        if request(reference(&caps).as_str()).is_some() {
+            let statement = caps.get(0).unwrap();
+            stripped.push_str(&content[last..statement.start()]);
+            last = statement.end();
+        }
+    }
+    stripped.push_str(&content[last..]);
+
+    stripped.trim_start().to_string()
+}
+
+/// Replace each range of `content` with its text. Ranges must not overlap.
+pub(crate) fn replace_ranges(content: &str, mut replacements: Vec<(Range, String)>) -> String {
+    replacements.sort_by_key(|(range, _)| range.start);
+    let mut replaced = String::with_capacity(content.len());
+    let mut last = 0;
+
+    for (range, text) in replacements {
+        replaced.push_str(&content[last..range.start]);
+        replaced.push_str(&text);
+        last = range.end;
+    }
+    replaced.push_str(&content[last..]);
+
+    replaced
+}
+
+/// Compile the stylesheet `content` for the browsers `query` selects:
+/// prefix properties and selectors they need prefixed and lower syntax they
+/// lack, such as nesting. `minify` also drops whitespace and merges rules.
+pub(crate) fn compile(
+    content: &str,
+    path: &Path,
+    query: Option<&str>,
+    minify: bool,
+) -> Result<String> {
+    let error = |message: String| BundlerError::ParseError {
+        file: path.display().to_string(),
+        message,
+    };
+
+    let browsers = match query {
+        Some(query) => Browsers::from_browserslist([query])
+            .map_err(|e| BundlerError::ConfigError(format!("Invalid target '{}': {}", query, e)))?,
+        None => None,
+    };
+    let targets = Targets {
+        browsers,
+        ..Targets::default()
+    };
+
+    let mut stylesheet = StyleSheet::parse(
+        content,
+        ParserOptions {
+            filename: path.display().to_string(),
+            flags: ParserFlags::NESTING,
+            ..ParserOptions::default()
+        },
+    )
+    .map_err(|e| error(e.to_string()))?;
+    stylesheet
+        .minify(MinifyOptions {
+            targets,
+            ..MinifyOptions::default()
+        })
+        .map_err(|e| error(e.to_string()))?;
+    let css = stylesheet
+        .to_css(PrinterOptions {
+            minify,
+            targets,
+            ..PrinterOptions::default()
+        })
+        .map_err(|e| error(e.to_string()))?;
+
+    Ok(css.code)
+}
+
+/// Script adding `css` to the document in a `<style>` element
+pub(crate) fn inject_style(css: &str) -> String {
+    let css = serde_json::to_string(css).unwrap_or_default();
+    format!(
+        "var style = document.createElement(\"style\");\nstyle.textContent = {};\ndocument.head.appendChild(style);",
+        css
+    )
+}
+
+/// Pairs of stylesheets that come in one order in one chunk and in the
+/// other order in another, so no order of the extracted CSS satisfies both
+pub(crate) fn order_conflicts<'a>(chunks: &[Vec<&'a str>]) -> Vec<(&'a str, &'a str)> {
+    let mut conflicts = Vec::new();
+
+    for (index, chunk) in chunks.iter().enumerate() {
+        for other in &chunks[index + 1..] {
+            let position = |id: &str| other.iter().position(|other_id| *other_id == id);
+            for (i, first) in chunk.iter().enumerate() {
+                for second in &chunk[i + 1..] {
+                    if let (Some(a), Some(b)) = (position(first), position(second))
+                        && b < a
+                        && !conflicts.contains(&(*first, *second))
+                    {
+                        conflicts.push((*first, *second));
+                    }
+                }
+            }
+        }
+    }
+
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_rewrite() {
+        let css = r#"@import "base.css";
+@import url('~normalize.css/normalize.css') screen;
+@import url(https://fonts.example.com/inter.css);
+/* url(commented.png) */
+.logo { background: url("./images/logo.png") no-repeat, url(data:image/png;base64,AAAA); }
+.icon { mask: url(icons.svg#star); }
+"#;
+
+        let dependencies = parse_dependencies(css);
+        let requests = |deps: &[Dependency]| {
+            deps.iter()
+                .map(|dep| dep.request.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            requests(&dependencies.imports),
+            ["./base.css", "normalize.css/normalize.css"]
+        );
+        assert_eq!(
+            requests(&dependencies.urls),
+            ["./images/logo.png", "./icons.svg#star"]
+        );
+        assert_eq!(dependencies.urls[1].fragment.as_deref(), Some("#star"));
+
+        let replacements = dependencies
+            .urls
+            .iter()
+            .map(|dep| {
+                (
+                    dep.range.clone().unwrap(),
+                    format!("assets/{}", dep.request.len()),
+                )
+            })
+            .collect();
+        let rewritten = strip_imports(&replace_ranges(css, replacements));
+        assert!(rewritten.starts_with("@import url(https://fonts.example.com/inter.css);"));
+        assert!(rewritten.contains(r#"url("assets/17") no-repeat"#));
+        assert!(rewritten.contains("url(assets/16);"));
+    }
+
+    #[test]
+    fn test_order_conflicts() {
+        let chunks = vec![
+            vec!["a.css", "b.css", "c.css"],
+            vec!["c.css", "a.css"],
+            vec!["b.css"],
+        ];
+        assert_eq!(order_conflicts(&chunks), [("a.css", "c.css")]);
+        assert!(order_conflicts(&chunks[1..]).is_empty());
+    }
+
+    #[test]
+    fn test_compile_for_targets() {
+        let path = Path::new("app.css");
+        let css = compile(
+            ".app { user-select: none; & .title { color: #ff0000; } }",
+            path,
+            Some("safari 13"),
+            false,
+        )
+        .unwrap();
+        assert!(css.contains("-webkit-user-select: none;"));
+        assert!(css.contains(".app .title {"));
+
+        assert_eq!(
+            compile(".app { color: #ff0000; }", path, None, true).unwrap(),
+            ".app{color:red}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extract_and_inject() {
+        use crate::{Config, compiler::Compiler, config::Mode};
+        use std::fs;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(
+            root.join("index.js"),
+            "import './app.css';\nimport './theme.css';\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("app.css"),
+            "@import './reset.css';\n.app { background: url(logo.png); }",
+        )
+        .unwrap();
+        fs::write(root.join("reset.css"), "* { margin: 0; }").unwrap();
+        fs::write(root.join("theme.css"), ".app { color: red; }").unwrap();
+        fs::write(root.join("logo.png"), [0x89, b'P', b'N', b'G']).unwrap();
+
+        let compile = |mode| async move {
+            let config = Config {
+                entry: root.join("index.js").display().to_string(),
+                mode,
+                ..Config::default()
+            };
+            Compiler::new(config).compile().await.unwrap()
+        };
+
+        let result = compile(Mode::Production).await;
+        let logo = format!("assets/logo.{}.png", crate::hash::content_hash(b"\x89PNG"));
+        assert_eq!(result.assets[&logo], b"\x89PNG");
+        assert_eq!(
+            String::from_utf8_lossy(&result.assets["main.css"]),
+            format!(
+                "*{{margin:0}}\n.app{{background:url({})}}\n.app{{color:red}}",
+                logo
+            )
+        );
+
+        let result = compile(Mode::Development).await;
+        assert!(!result.assets.contains_key("main.css"));
+        let bundle = String::from_utf8_lossy(&result.assets["main.js"]);
+        assert!(bundle.contains("style.textContent = \".app {\\n  color: red;\\n}\\n\""));
+    }
+}
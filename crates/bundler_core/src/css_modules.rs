@@ -0,0 +1,419 @@
+//! CSS Modules: class names scoped to the stylesheet that defines them
+//!
+//! Every class selector of a `*.module.css` file is renamed after the
+//! configured pattern, and the module's script exports the generated names
+//! keyed by the names in the source. `:global(...)` opts selectors out.
+//! Keyframes and other at-rule names stay global.
+
+use crate::{
+    BundlerError, Result,
+    css::{mask_comments, replace_ranges, request},
+    dependency::Range,
+};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// A CSS module with its class names scoped
+pub(crate) struct ScopedCss {
+    pub css: String,
+    /// Class names to put on an element for each class of the source: its
+    /// own scoped name, followed by those of the classes it composes
+    pub classes: BTreeMap<String, String>,
+}
+
+/// What a `composes:` declaration adds to a class
+#[derive(Clone)]
+enum Composed {
+    /// Another class of the same module
+    Local(String),
+    /// A class name used as is
+    Global(String),
+    /// A class of another CSS module, by the request it is composed from
+    Imported { request: String, name: String },
+}
+
+enum Block {
+    /// A style rule and, if its selectors are all single classes, the
+    /// classes declarations like `composes` apply to
+    Rule(Option<Vec<String>>),
+    Keyframes,
+    AtRule,
+}
+
+/// Whether `path` is a CSS module, e.g. `button.module.css`
+pub(crate) fn is_css_module(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|stem| Path::new(stem).extension())
+        .is_some_and(|extension| extension == "module")
+}
+
+/// Scope the classes of the CSS module `content`, whose path relative to the
+/// project is `path`, after `pattern`. `imported` looks up the names another
+/// module exports for a class, given the request it is composed from.
+pub(crate) fn scope(
+    content: &str,
+    path: &Path,
+    pattern: &str,
+    imported: impl Fn(&str, &str) -> Option<String>,
+) -> Result<ScopedCss> {
+    let error = |message: String| BundlerError::ParseError {
+        file: path.display().to_string(),
+        message,
+    };
+    let scoped_name = |local: &str| local_ident_name(pattern, path, local);
+
+    let masked = mask_comments(content);
+    let mut replacements = Vec::new();
+    let mut composes: BTreeMap<String, Vec<Composed>> = BTreeMap::new();
+    let mut blocks = Vec::new();
+    let mut start = 0;
+
+    for (index, c) in masked.char_indices() {
+        match c {
+            '{' => {
+                let prelude = masked[start..index].trim_start();
+                let block = if prelude.starts_with('@') {
+                    if prelude.to_ascii_lowercase().contains("keyframes") {
+                        Block::Keyframes
+                    } else {
+                        Block::AtRule
+                    }
+                } else if matches!(blocks.last(), Some(Block::Keyframes)) {
+                    // `from`, `50%`, ...
+                    Block::AtRule
+                } else {
+                    let selector = &masked[start..index];
+                    for local in scope_selector(selector, start, &mut replacements, &scoped_name) {
+                        composes.entry(local).or_default();
+                    }
+                    Block::Rule(single_classes(selector))
+                };
+                blocks.push(block);
+                start = index + 1;
+            }
+            ';' | '}' => {
+                if let Some(Block::Rule(classes)) = blocks.last()
+                    && let Some(composed) = parse_composes(&masked[start..index]).map_err(error)?
+                {
+                    let classes = classes.as_ref().ok_or_else(|| {
+                        error("`composes` is only allowed in rules for single classes".to_string())
+                    })?;
+                    for local in classes {
+                        composes
+                            .entry(local.clone())
+                            .or_default()
+                            .extend(composed.iter().cloned());
+                    }
+                    let end = if c == ';' { index + 1 } else { index };
+                    replacements.push((Range { start, end }, String::new()));
+                }
+                if c == '}' {
+                    blocks.pop();
+                }
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    let mut classes = BTreeMap::new();
+    for local in composes.keys() {
+        let mut names = Vec::new();
+        let mut stack = Vec::new();
+        class_names(
+            local,
+            &composes,
+            &scoped_name,
+            &imported,
+            &mut stack,
+            &mut names,
+        )
+        .map_err(error)?;
+        classes.insert(local.clone(), names.join(" "));
+    }
+
+    Ok(ScopedCss {
+        css: replace_ranges(content, replacements),
+        classes,
+    })
+}
+
+/// Scope the classes of the selector `selector`, found at `offset`, and
+/// return the local ones
+fn scope_selector(
+    selector: &str,
+    offset: usize,
+    replacements: &mut Vec<(Range, String)>,
+    scoped_name: &impl Fn(&str) -> String,
+) -> Vec<String> {
+    let class_regex = Regex::new(r"\.(-?[_a-zA-Z][\w-]*)").unwrap();
+    let selector_regex =
+        Regex::new(r":global\(([^)]*)\)|:local\(([^)]*)\)|\.(-?[_a-zA-Z][\w-]*)").unwrap();
+    let mut locals = Vec::new();
+
+    for caps in selector_regex.captures_iter(selector) {
+        let whole = caps.get(0).unwrap();
+        let range = Range {
+            start: offset + whole.start(),
+            end: offset + whole.end(),
+        };
+        if let Some(global) = caps.get(1) {
+            replacements.push((range, global.as_str().to_string()));
+        } else if let Some(local) = caps.get(2) {
+            let scoped = class_regex.replace_all(local.as_str(), |caps: &regex::Captures| {
+                locals.push(caps[1].to_string());
+                format!(".{}", scoped_name(&caps[1]))
+            });
+            replacements.push((range, scoped.into_owned()));
+        } else {
+            let class = &caps[3];
+            locals.push(class.to_string());
+            replacements.push((range, format!(".{}", scoped_name(class))));
+        }
+    }
+
+    locals
+}
+
+/// The classes of `selector` if it is a list of single classes like
+/// `.a, .b`
+fn single_classes(selector: &str) -> Option<Vec<String>> {
+    let class_regex = Regex::new(r"^\.(-?[_a-zA-Z][\w-]*)$").unwrap();
+    selector
+        .split(',')
+        .map(|selector| {
+            class_regex
+                .captures(selector.trim())
+                .map(|caps| caps[1].to_string())
+        })
+        .collect()
+}
+
+/// The classes a `composes: a b [from "./other.module.css" | global]`
+/// declaration adds, or `None` if `declaration` is something else
+fn parse_composes(declaration: &str) -> std::result::Result<Option<Vec<Composed>>, String> {
+    let Some((property, value)) = declaration.split_once(':') else {
+        return Ok(None);
+    };
+    if property.trim() != "composes" {
+        return Ok(None);
+    }
+
+    let from_regex = Regex::new(r#"^(.+?)\s+from\s+(?:"([^"]*)"|'([^']*)'|(global))$"#).unwrap();
+    let value = value.trim();
+    let (names, source) = match from_regex.captures(value) {
+        Some(caps) => (
+            caps.get(1).unwrap().as_str(),
+            caps.get(2).or(caps.get(3)).or(caps.get(4)),
+        ),
+        None => (value, None),
+    };
+
+    let imported_from = match source.map(|source| source.as_str()) {
+        None | Some("global") => None,
+        Some(url) => {
+            Some(request(url).ok_or_else(|| format!("cannot compose classes from '{}'", url))?)
+        }
+    };
+
+    let composed = names
+        .split_whitespace()
+        .map(|name| match (&imported_from, source) {
+            (Some(request), _) => Composed::Imported {
+                request: request.clone(),
+                name: name.to_string(),
+            },
+            (None, Some(_)) => Composed::Global(name.to_string()),
+            (None, None) => Composed::Local(name.to_string()),
+        })
+        .collect::<Vec<_>>();
+    if composed.is_empty() {
+        return Err("`composes` names no classes".to_string());
+    }
+
+    Ok(Some(composed))
+}
+
+/// Add the class names of `local` and everything it composes to `names`
+fn class_names(
+    local: &str,
+    composes: &BTreeMap<String, Vec<Composed>>,
+    scoped_name: &impl Fn(&str) -> String,
+    imported: &impl Fn(&str, &str) -> Option<String>,
+    stack: &mut Vec<String>,
+    names: &mut Vec<String>,
+) -> std::result::Result<(), String> {
+    if stack.iter().any(|seen| seen == local) {
+        return Err(format!("class '{}' composes itself", local));
+    }
+    let Some(composed) = composes.get(local) else {
+        return Err(format!("cannot compose unknown class '{}'", local));
+    };
+    stack.push(local.to_string());
+
+    add_name(names, &scoped_name(local));
+    for item in composed {
+        match item {
+            Composed::Local(other) => {
+                class_names(other, composes, scoped_name, imported, stack, names)?
+            }
+            Composed::Global(name) => add_name(names, name),
+            Composed::Imported { request, name } => {
+                let imported_names = imported(request, name).ok_or_else(|| {
+                    format!("'{}' does not export a class named '{}'", request, name)
+                })?;
+                for name in imported_names.split_whitespace() {
+                    add_name(names, name);
+                }
+            }
+        }
+    }
+
+    stack.pop();
+    Ok(())
+}
+
+fn add_name(names: &mut Vec<String>, name: &str) {
+    if !names.iter().any(|existing| existing == name) {
+        names.push(name.to_string());
+    }
+}
+
+/// Scoped name of the class `local` of the module at `path`. `pattern` may
+/// use `[name]`, the file name without `.module.css`, `[local]` and
+/// `[hash]`, optionally shortened like `[hash:5]`.
+fn local_ident_name(pattern: &str, path: &Path, local: &str) -> String {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let name = name.split('.').next().unwrap_or_default();
+    let path = path.to_string_lossy().replace('\\', "/");
+    let hash = crate::hash::content_hash(format!("{}:{}", path, local).as_bytes());
+
+    let ident = Regex::new(r"\[hash(?::(\d+))?\]")
+        .unwrap()
+        .replace_all(pattern, |caps: &regex::Captures| {
+            let length = caps
+                .get(1)
+                .and_then(|length| length.as_str().parse().ok())
+                .unwrap_or(hash.len());
+            hash[..length.min(hash.len())].to_string()
+        })
+        .replace("[name]", name)
+        .replace("[local]", local);
+
+    // Whatever the pattern produced has to be a valid identifier
+    let ident = Regex::new(r"[^\w-]")
+        .unwrap()
+        .replace_all(&ident, "_")
+        .into_owned();
+    if ident.starts_with(|c: char| c.is_ascii_digit()) || ident.starts_with("--") {
+        format!("_{}", ident)
+    } else {
+        ident
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_and_composes() {
+        let css = r#"/* .commented {} */
+.base { padding: 0; }
+.button { composes: base; composes: link from "./link.module.css"; color: red; }
+.primary, .accent { composes: button; composes: shared from global }
+:global(.theme-dark) .button:hover { color: blue; }
+@media (min-width: 600px) { .base { padding: 1.5em; } }
+@keyframes spin { from { opacity: 0; } 50.5% { opacity: 1; } }
+"#;
+        let path = Path::new("src/button.module.css");
+        let scoped = scope(css, path, "[name]_[local]", |request, name| {
+            (request == "./link.module.css" && name == "link").then(|| "link_link".to_string())
+        })
+        .unwrap();
+
+        assert_eq!(
+            scoped.css,
+            r#"/* .commented {} */
+.button_base { padding: 0; }
+.button_button { color: red; }
+.button_primary, .button_accent {}
+.theme-dark .button_button:hover { color: blue; }
+@media (min-width: 600px) { .button_base { padding: 1.5em; } }
+@keyframes spin { from { opacity: 0; } 50.5% { opacity: 1; } }
+"#
+        );
+        assert_eq!(
+            scoped.classes,
+            BTreeMap::from([
+                (
+                    "accent".to_string(),
+                    "button_accent button_button button_base link_link shared".to_string()
+                ),
+                ("base".to_string(), "button_base".to_string()),
+                (
+                    "button".to_string(),
+                    "button_button button_base link_link".to_string()
+                ),
+                (
+                    "primary".to_string(),
+                    "button_primary button_button button_base link_link shared".to_string()
+                ),
+            ])
+        );
+
+        let hashed = local_ident_name("[hash:5]", path, "base");
+        assert_eq!(hashed.len(), 5 + usize::from(hashed.starts_with('_')));
+        assert!(scope(".a { composes: b; }", path, "[local]", |_, _| None).is_err());
+        assert!(scope(".a .b { composes: a; }", path, "[local]", |_, _| None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_exports_through_compiler() {
+        use crate::{Config, compiler::Compiler, config::Mode};
+        use std::fs;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::write(
+            root.join("index.js"),
+            "import styles from './card.module.css';\nconsole.log(styles.card);\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("card.module.css"),
+            ".card { composes: base from './base.module.css'; }",
+        )
+        .unwrap();
+        fs::write(root.join("base.module.css"), ".base { margin: 0; }").unwrap();
+
+        let pattern = "[name]-[local]-[hash:5]";
+        let mut config = Config {
+            context: Some(root.display().to_string()),
+            entry: root.join("index.js").display().to_string(),
+            mode: Mode::Production,
+            ..Config::default()
+        };
+        config.css.modules.local_ident_name = pattern.to_string();
+        let result = Compiler::new(config).compile().await.unwrap();
+
+        // Hashes are of paths from the project root, whatever the working
+        // directory
+        let name = |file: &str, local| local_ident_name(pattern, Path::new(file), local);
+        assert_eq!(
+            String::from_utf8_lossy(&result.assets["main.css"]),
+            // The rule left empty by `composes` is minified away
+            format!(".{}{{margin:0}}\n", name("base.module.css", "base"))
+        );
+        let bundle = String::from_utf8_lossy(&result.assets["main.js"]);
+        assert!(bundle.contains(&format!(
+            r#"module.exports = {{"card":"{} {}"}};"#,
+            name("card.module.css", "card"),
+            name("base.module.css", "base")
+        )));
+    }
+}
@@ -5,6 +5,12 @@ pub struct Dependency {
     pub request: String,
     pub dependency_type: DependencyType,
     pub range: Option<Range>,
+    /// Resource query of the request, including the leading `?`
+    pub query: Option<String>,
+    /// Fragment of the request, including the leading `#`
+    pub fragment: Option<String>,
+    /// Id of the module the request resolved to, once it has been resolved
+    pub module_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,6 +19,13 @@ pub enum DependencyType {
     Require,
     DynamicImport,
     ImportMeta,
+    /// `@import` in a stylesheet
+    CssImport,
+    /// `url()` in a stylesheet
+    CssUrl,
+    /// Script a worker runs: `new Worker(new URL(...))`, `new
+    /// SharedWorker(...)` or `navigator.serviceWorker.register(...)`
+    Worker,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,10 +36,17 @@ pub struct Range {
 
 impl Dependency {
     pub fn new(request: String, dependency_type: DependencyType) -> Self {
+        let parsed = bundler_resolver::parse_request(&request);
+        let query = parsed.query.map(str::to_string);
+        let fragment = parsed.fragment.map(str::to_string);
+
         Self {
             request,
             dependency_type,
             range: None,
+            query,
+            fragment,
+            module_id: None,
         }
     }
 
@@ -34,4 +54,37 @@ impl Dependency {
         self.range = Some(Range { start, end });
         self
     }
+
+    /// 1-based line and column of the request in `source`, what the module
+    /// was before its loaders ran. Loaders move code around, so the request
+    /// is looked up as a string literal or `url()` there, falling back to
+    /// its range in the loaded `content`.
+    pub fn line_column(&self, source: &str, content: &str) -> (usize, usize) {
+        let request = &self.request;
+        let written = ['"', '\'', '`']
+            .iter()
+            .map(|quote| format!("{quote}{request}{quote}"))
+            .chain([format!("url({request})")])
+            .filter_map(|literal| {
+                // The range starts at the request, after the quote or `url(`
+                let opening = literal.len() - request.len() - 1;
+                source.find(&literal).map(|index| index + opening)
+            })
+            .min();
+        match (written, &self.range) {
+            (Some(start), _) => Range { start, end: start }.line_column(source),
+            (None, Some(range)) => range.line_column(content),
+            (None, None) => (1, 1),
+        }
+    }
+}
+
+impl Range {
+    /// 1-based line and column of the start of the range within `source`
+    pub fn line_column(&self, source: &str) -> (usize, usize) {
+        let before = &source[..self.start.min(source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+        (line, column)
+    }
 }
@@ -23,6 +23,17 @@ pub enum BundlerError {
     #[error("Configuration error: {0}")]
     ConfigError(String),
 
+    /// Every import has to resolve for the build to succeed. Imports that
+    /// don't used to be left out of the module graph without a word.
+    #[error("Cannot resolve '{request}' imported at {file}:{line}:{column}\n{source}")]
+    UnresolvedImport {
+        request: String,
+        file: String,
+        line: usize,
+        column: usize,
+        source: Box<bundler_resolver::ResolverError>,
+    },
+
     #[error("Resolver error: {0}")]
     ResolverError(#[from] bundler_resolver::ResolverError),
 }
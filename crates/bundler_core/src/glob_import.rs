@@ -0,0 +1,536 @@
+//! Glob imports
+//!
+//! Vite's `import.meta.glob()` and webpack's `require.context()` stand for
+//! every module matching a pattern, which is what file-system routing is
+//! built on. Both are expanded while building into a map of the matching
+//! modules, each required eagerly or loaded with `import()`, and those
+//! requests are then bundled like any other.
+
+use crate::{BundlerError, Result};
+use glob::{MatchOptions, Pattern};
+use regex::Regex;
+use serde_json::Value;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// `*` and `?` don't match `/`, like in Vite
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Expand the `import.meta.glob()` and `require.context()` calls of the
+/// module at `path`. Relative patterns are taken from `dir`, ones
+/// starting with `/` from `root`. Calls mentioned in comments and strings
+/// are left alone.
+pub(crate) fn expand(content: &str, path: &Path, dir: &Path, root: &Path) -> Result<String> {
+    const GLOB_CALL: &str = "import.meta.glob(";
+    let error = |message: String| BundlerError::ParseError {
+        file: path.display().to_string(),
+        message,
+    };
+
+    let skipped = comments_and_strings(content);
+    let mut expanded = String::new();
+    let mut last = 0;
+    let mut from = 0;
+    while let Some(start) = content[from..].find(GLOB_CALL).map(|i| from + i) {
+        from = start + GLOB_CALL.len();
+        if skipped.iter().any(|range| range.contains(&start)) {
+            continue;
+        }
+        let end = closing_paren(content, from)
+            .ok_or_else(|| error("unterminated import.meta.glob() call".to_string()))?;
+        let arguments: Vec<Value> = json5::from_str(&format!("[{}]", &content[from..end]))
+            .map_err(|e| error(format!("invalid import.meta.glob() arguments: {}", e)))?;
+
+        expanded.push_str(&content[last..start]);
+        expanded.push_str(&expand_glob(&arguments, path, dir, root).map_err(error)?);
+        last = end + 1;
+        from = last;
+    }
+    expanded.push_str(&content[last..]);
+
+    let skipped = comments_and_strings(&expanded);
+    let mut error_at = None;
+    let expanded = context_regex().replace_all(&expanded, |caps: &regex::Captures| {
+        let start = caps.get(0).unwrap().start();
+        if skipped.iter().any(|range| range.contains(&start)) {
+            return caps[0].to_string();
+        }
+        let call = RequireContext {
+            directory: &caps[1],
+            recursive: caps.get(2).is_none_or(|m| m.as_str() == "true"),
+            regex: caps.get(3).map_or(r"^\./.*$", |m| m.as_str()),
+            flags: caps.get(4).map_or("", |m| m.as_str()),
+            mode: caps.get(5).map_or("sync", |m| m.as_str()),
+        };
+        match call.expand(path, dir) {
+            Ok(map) => map,
+            Err(e) => {
+                error_at.get_or_insert(e);
+                String::new()
+            }
+        }
+    });
+    match error_at {
+        Some(e) => Err(e),
+        None => Ok(expanded.into_owned()),
+    }
+}
+
+/// Byte ranges of the comments and string literals of `content`. Template
+/// literals count as strings as a whole, and regex literals aren't told
+/// apart from code.
+fn comments_and_strings(content: &str) -> Vec<Range<usize>> {
+    let bytes = content.as_bytes();
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'/', Some(b'/')) => {
+                i = content[i..].find('\n').map_or(bytes.len(), |end| i + end);
+            }
+            (b'/', Some(b'*')) => {
+                i = content[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| i + 2 + end + 2);
+            }
+            (quote @ (b'"' | b'\'' | b'`'), _) => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(bytes.len());
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        }
+        ranges.push(start..i);
+    }
+    ranges
+}
+
+/// Index of the `)` closing the call whose arguments start at `start`,
+/// skipping over strings and nested brackets
+fn closing_paren(content: &str, start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in content[start..].char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' | '`' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' if depth == 0 => return Some(start + i),
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Object mapping the files an `import.meta.glob()` call with `arguments`
+/// matches to their modules, or to functions importing them unless the
+/// `eager` option is set. The `import` option picks one export.
+fn expand_glob(
+    arguments: &[Value],
+    path: &Path,
+    dir: &Path,
+    root: &Path,
+) -> std::result::Result<String, String> {
+    let patterns: Vec<&str> = match arguments.first() {
+        Some(Value::String(pattern)) => vec![pattern],
+        Some(Value::Array(patterns)) => patterns
+            .iter()
+            .map(|pattern| pattern.as_str().ok_or("patterns must be strings"))
+            .collect::<std::result::Result<_, _>>()?,
+        _ => return Err("import.meta.glob() expects a pattern or an array of them".to_string()),
+    };
+    let options = arguments.get(1);
+    let eager = options
+        .and_then(|options| options.get("eager"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let export = options
+        .and_then(|options| options.get("import"))
+        .and_then(Value::as_str)
+        .filter(|name| *name != "*");
+
+    let (negated, patterns): (Vec<&str>, Vec<&str>) =
+        patterns.into_iter().partition(|p| p.starts_with('!'));
+    let negated = negated
+        .into_iter()
+        .map(|pattern| GlobPattern::new(&pattern[1..], dir, root))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut matches = Vec::new();
+    for pattern in patterns {
+        let pattern = GlobPattern::new(pattern, dir, root)?;
+        let paths = glob::glob_with(pattern.pattern.as_str(), MATCH_OPTIONS)
+            .map_err(|e| format!("invalid glob pattern '{}': {}", pattern.pattern, e))?;
+        for matched in paths {
+            let matched = matched.map_err(|e| e.to_string())?;
+            if !matched.is_file()
+                || matched == path
+                || negated
+                    .iter()
+                    .any(|n| n.pattern.matches_path_with(&matched, MATCH_OPTIONS))
+            {
+                continue;
+            }
+            matches.push(pattern.key(&matched));
+        }
+    }
+    matches.sort();
+    matches.dedup();
+
+    let entries: Vec<String> = matches
+        .iter()
+        .map(|(key, request)| {
+            let request = js_string(request);
+            let value = match (eager, export) {
+                (true, None) => format!("require({})", request),
+                // Read the way default imports are
+                (true, Some("default")) => {
+                    format!("__webpack_require__.n(require({}))", request)
+                }
+                (true, Some(name)) => format!("require({}).{}", request, name),
+                (false, None) => format!("function() {{ return import({}); }}", request),
+                (false, Some("default")) => format!(
+                    "function() {{ return import({}).then(__webpack_require__.n); }}",
+                    request
+                ),
+                (false, Some(name)) => format!(
+                    "function() {{ return import({}).then(function(m) {{ return m.{}; }}); }}",
+                    request, name
+                ),
+            };
+            format!("  {}: {}", js_string(key), value)
+        })
+        .collect();
+    Ok(object(&entries))
+}
+
+/// A pattern of `import.meta.glob()`, anchored at the directory its `../`s
+/// lead to, or at the root for `/` patterns
+struct GlobPattern {
+    pattern: Pattern,
+    base: PathBuf,
+    /// What keys start with: `/`, `./` or some `../`s
+    prefix: String,
+}
+
+impl GlobPattern {
+    fn new(pattern: &str, dir: &Path, root: &Path) -> std::result::Result<Self, String> {
+        let (mut base, mut prefix, mut rest) = match pattern.strip_prefix('/') {
+            Some(rest) => (root.to_path_buf(), "/".to_string(), rest),
+            None => (
+                dir.to_path_buf(),
+                String::new(),
+                pattern.strip_prefix("./").unwrap_or(pattern),
+            ),
+        };
+        while let Some(parent) = rest.strip_prefix("../") {
+            base.pop();
+            prefix.push_str("../");
+            rest = parent;
+        }
+        if prefix.is_empty() {
+            prefix.push_str("./");
+        }
+
+        let full = format!("{}/{}", Pattern::escape(&base.display().to_string()), rest);
+        let pattern = Pattern::new(&full)
+            .map_err(|e| format!("invalid glob pattern '{}': {}", pattern, e))?;
+        Ok(Self {
+            pattern,
+            base,
+            prefix,
+        })
+    }
+
+    /// Key of the file at `path` in the expanded object, and its request.
+    /// Files under the root are requested by their absolute path.
+    fn key(&self, path: &Path) -> (String, String) {
+        let relative = path.strip_prefix(&self.base).unwrap_or(path);
+        let key = format!(
+            "{}{}",
+            self.prefix,
+            relative.display().to_string().replace('\\', "/")
+        );
+        let request = if self.prefix == "/" {
+            path.display().to_string()
+        } else {
+            key.clone()
+        };
+        (key, request)
+    }
+}
+
+/// Matches `require.context(directory, recursive, /regex/flags, mode)`,
+/// whose arguments after the directory are optional
+fn context_regex() -> Regex {
+    Regex::new(
+        r#"require\.context\(\s*['"]([^'"]*)['"]\s*(?:,\s*(true|false)\s*(?:,\s*/((?:[^/\\\n]|\\.)+)/([a-z]*)\s*(?:,\s*['"]([\w-]+)['"]\s*)?)?)?\)"#,
+    )
+    .unwrap()
+}
+
+/// The arguments of a `require.context()` call
+struct RequireContext<'a> {
+    directory: &'a str,
+    recursive: bool,
+    /// Source of the regex the keys are tested with, as written in JS
+    regex: &'a str,
+    flags: &'a str,
+    mode: &'a str,
+}
+
+impl RequireContext<'_> {
+    /// The context function of the call, made by the module at `path`:
+    /// calling it with a key requires the module, or imports it in `lazy`
+    /// and `eager` mode, and `keys()` lists the keys, which are the paths
+    /// of the files from `directory` passing the regex, like `./a.js`
+    fn expand(&self, path: &Path, dir: &Path) -> Result<String> {
+        let error = |message: String| BundlerError::ParseError {
+            file: path.display().to_string(),
+            message,
+        };
+        let (flags, ignored): (String, String) = self
+            .flags
+            .chars()
+            .partition(|flag| matches!(flag, 'i' | 'm' | 's'));
+        if let Some(flag) = ignored
+            .chars()
+            .find(|flag| !matches!(flag, 'g' | 'u' | 'y'))
+        {
+            return Err(error(format!("unsupported regex flag '{}'", flag)));
+        }
+        let source = if flags.is_empty() {
+            self.regex.to_string()
+        } else {
+            format!("(?{}){}", flags, self.regex)
+        };
+        let regex = Regex::new(&source)
+            .map_err(|e| error(format!("invalid require.context() regex: {}", e)))?;
+        if !matches!(self.mode, "sync" | "eager" | "lazy") {
+            return Err(error(format!(
+                "unsupported require.context() mode '{}'",
+                self.mode
+            )));
+        }
+
+        let directory = self.directory.trim_end_matches('/');
+        let base = dir.join(directory);
+        if !base.is_dir() {
+            return Err(BundlerError::ModuleNotFound {
+                path: base.display().to_string(),
+            });
+        }
+
+        let walker = WalkDir::new(&base)
+            .max_depth(if self.recursive { usize::MAX } else { 1 })
+            .sort_by_file_name();
+        let mut entries = Vec::new();
+        for entry in walker {
+            let entry = entry.map_err(std::io::Error::from)?;
+            if !entry.file_type().is_file() || entry.path() == path {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(&base).unwrap_or(entry.path());
+            let relative = relative.display().to_string().replace('\\', "/");
+            let key = format!("./{}", relative);
+            if !regex.is_match(&key) {
+                continue;
+            }
+
+            let request = js_string(&format!("{}/{}", directory, relative));
+            let value = match self.mode {
+                "sync" => format!("require({})", request),
+                "eager" => format!(
+                    "Promise.resolve().then(function() {{ return require({}); }})",
+                    request
+                ),
+                _ => format!("import({})", request),
+            };
+            entries.push(format!(
+                "    {}: function() {{ return {}; }}",
+                js_string(&key),
+                value
+            ));
+        }
+
+        let missing = if self.mode == "sync" {
+            "throw error;"
+        } else {
+            "return Promise.reject(error);"
+        };
+        let id = format!(
+            "{} {}{} /{}/{}",
+            directory,
+            self.mode,
+            if self.recursive { " recursive" } else { "" },
+            self.regex,
+            self.flags
+        );
+        Ok(format!(
+            r#"(function() {{
+  var map = {};
+  function context(key) {{
+    if (!Object.prototype.hasOwnProperty.call(map, key)) {{
+      var error = new Error("Cannot find module '" + key + "'");
+      error.code = "MODULE_NOT_FOUND";
+      {}
+    }}
+    return map[key]();
+  }}
+  context.keys = function() {{ return Object.keys(map); }};
+  context.id = {};
+  return context;
+}})()"#,
+            object(&entries).replace("\n}", "\n  }"),
+            missing,
+            js_string(&id)
+        ))
+    }
+}
+
+fn object(entries: &[String]) -> String {
+    if entries.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{\n{}\n}}", entries.join(",\n"))
+    }
+}
+
+fn js_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| format!("{:?}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_expand() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src/pages/blog")).unwrap();
+        fs::create_dir(root.join("src/components")).unwrap();
+        for file in [
+            "src/pages/index.tsx",
+            "src/pages/about.tsx",
+            "src/pages/_draft.tsx",
+            "src/pages/blog/post.tsx",
+            "src/components/Button.js",
+            "src/components/button.css",
+            "src/components/nested/Icon.js",
+        ] {
+            fs::create_dir_all(root.join(file).parent().unwrap()).unwrap();
+            fs::write(root.join(file), "").unwrap();
+        }
+        let path = root.join("src/main.js");
+        let dir = root.join("src");
+
+        let content = "const pages = import.meta.glob(['./pages/*.tsx', '!**/_*'], { eager: true, import: 'render' });";
+        assert_eq!(
+            expand(content, &path, &dir, root).unwrap(),
+            "const pages = {\n  \"./pages/about.tsx\": require(\"./pages/about.tsx\").render,\n  \"./pages/index.tsx\": require(\"./pages/index.tsx\").render\n};"
+        );
+
+        let content = "import.meta.glob('../src/pages/**/*.tsx', {\n  import: 'default',\n})";
+        let expanded = expand(content, &path, &dir, root).unwrap();
+        assert!(expanded.contains(
+            "\"../src/pages/blog/post.tsx\": function() { return import(\"../src/pages/blog/post.tsx\").then(__webpack_require__.n); }"
+        ));
+        assert_eq!(expanded.matches("function()").count(), 4);
+
+        let content = "import.meta.glob('/src/pages/a*.tsx')";
+        assert_eq!(
+            expand(content, &path, &dir, root).unwrap(),
+            format!(
+                "{{\n  \"/src/pages/about.tsx\": function() {{ return import({}); }}\n}}",
+                js_string(&root.join("src/pages/about.tsx").display().to_string())
+            )
+        );
+
+        let content = "const components = require.context('./components', false, /\\.JS$/i);";
+        let expanded = expand(content, &path, &dir, root).unwrap();
+        assert!(expanded.contains(
+            "    \"./Button.js\": function() { return require(\"./components/Button.js\"); }\n  };"
+        ));
+        assert!(!expanded.contains("Icon"));
+        assert!(expanded.contains("throw error;"));
+        assert!(expanded.contains("context.id = \"./components sync /\\\\.JS$/i\";"));
+
+        let content = "require.context('./components/', true, /\\.js$/, 'lazy')";
+        let expanded = expand(content, &path, &dir, root).unwrap();
+        assert!(expanded.contains("return import(\"./components/nested/Icon.js\");"));
+        assert!(expanded.contains("return Promise.reject(error);"));
+
+        assert!(matches!(
+            expand("require.context('./missing')", &path, &dir, root),
+            Err(BundlerError::ModuleNotFound { .. })
+        ));
+        assert!(matches!(
+            expand("import.meta.glob(42)", &path, &dir, root),
+            Err(BundlerError::ParseError { .. })
+        ));
+
+        // Only mentioned, not called
+        let content = "// import.meta.glob(pattern) maps files to modules\n/* require.context('./missing') */\nconst doc = \"import.meta.glob(\", tip = `require.context(dir)`;\nconst pages = import.meta.glob('./pages/a*.tsx');";
+        let expanded = expand(content, &path, &dir, root).unwrap();
+        assert!(expanded.starts_with(
+            "// import.meta.glob(pattern) maps files to modules\n/* require.context('./missing') */\nconst doc = \"import.meta.glob(\", tip = `require.context(dir)`;\nconst pages = {\n  \"./pages/about.tsx\""
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_root_patterns_through_compiler() {
+        use crate::{Config, compiler::Compiler};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("src/pages")).unwrap();
+        fs::write(
+            root.join("src/index.js"),
+            "const pages = import.meta.glob('/src/pages/*.js', { eager: true });\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/pages/home.js"),
+            "export const title = 'Home';\n",
+        )
+        .unwrap();
+
+        // `/` is the project root, not the directory the build runs in
+        let config = Config {
+            context: Some(root.display().to_string()),
+            entry: root.join("src/index.js").display().to_string(),
+            ..Config::default()
+        };
+        let result = Compiler::new(config).compile().await.unwrap();
+
+        let bundle = String::from_utf8_lossy(&result.assets["main.js"]);
+        assert!(bundle.contains(&format!(
+            "\"/src/pages/home.js\": __webpack_require__({:?})",
+            root.join("src/pages/home.js").display().to_string()
+        )));
+    }
+}
@@ -0,0 +1,12 @@
+//! Short content hashes for output filenames and generated names
+
+/// Hex FNV-1a hash of `bytes`, 8 characters long. Stable across runs and
+/// platforms, which is all a cache-busting filename needs.
+pub(crate) fn content_hash(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{:016x}", hash)[..8].to_string()
+}
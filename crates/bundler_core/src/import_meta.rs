@@ -0,0 +1,297 @@
+//! `import.meta` in bundled modules
+//!
+//! Bundles are classic scripts, where `import.meta` is a syntax error, so
+//! every use is replaced: `import.meta.url` with the URL of the running
+//! script, `import.meta.env` with values known at build time, the way Vite
+//! does it, and `import.meta.hot` with `undefined`, since bundles aren't
+//! hot-reloaded. `import.meta.resolve()` is resolved while building, to a
+//! `file:` URL on Node.js; on the web, resolving a file is an error.
+
+use crate::{
+    config::{Config, Mode, Platform},
+    dependency::{Dependency, DependencyType},
+};
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The `import.meta.env` of modules built with `config`: `MODE`, `DEV`,
+/// `PROD`, `BASE_URL` and `SSR`, then the variables starting with
+/// `config.env_prefix` from the `.env` files in `dir` and from `vars`, the
+/// environment
+pub(crate) fn env(
+    config: &Config,
+    dir: &Path,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Value {
+    let mode = match config.mode {
+        Mode::Development => "development",
+        Mode::Production => "production",
+    };
+    let base_url = match config.output.public_path.as_str() {
+        "" => "/",
+        public_path => public_path,
+    };
+
+    let mut env = Map::new();
+    env.insert("MODE".to_string(), mode.into());
+    env.insert(
+        "DEV".to_string(),
+        matches!(config.mode, Mode::Development).into(),
+    );
+    env.insert(
+        "PROD".to_string(),
+        matches!(config.mode, Mode::Production).into(),
+    );
+    env.insert("BASE_URL".to_string(), base_url.into());
+    env.insert(
+        "SSR".to_string(),
+        (config.platform == Platform::Node).into(),
+    );
+    for (key, value) in load_env(dir, mode, &config.env_prefix, vars) {
+        env.insert(key, value.into());
+    }
+
+    Value::Object(env)
+}
+
+/// Variables starting with `prefix` from `.env`, `.env.local`,
+/// `.env.<mode>` and `.env.<mode>.local` in `dir`, each file overriding the
+/// ones before it, and from the environment `vars`, which overrides them all
+fn load_env(
+    dir: &Path,
+    mode: &str,
+    prefix: &str,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> BTreeMap<String, String> {
+    let mut variables = BTreeMap::new();
+
+    let files = [
+        ".env".to_string(),
+        ".env.local".to_string(),
+        format!(".env.{}", mode),
+        format!(".env.{}.local", mode),
+    ];
+    for file in files {
+        if let Ok(content) = std::fs::read_to_string(dir.join(file)) {
+            variables.extend(parse_env_file(&content));
+        }
+    }
+    variables.extend(vars);
+
+    variables.retain(|key, _| key.starts_with(prefix));
+    variables
+}
+
+/// The `KEY=value` pairs of a `.env` file. Values may be quoted; double
+/// quotes understand `\n` escapes. `#` starts a comment outside quotes.
+fn parse_env_file(content: &str) -> Vec<(String, String)> {
+    let mut variables = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+
+        let value = value.trim();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let quoted = &value[1..];
+                let quoted = &quoted[..quoted.find(quote).unwrap_or(quoted.len())];
+                if quote == '"' {
+                    quoted.replace("\\n", "\n")
+                } else {
+                    quoted.to_string()
+                }
+            }
+            _ => value
+                .split(" #")
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+        };
+        variables.push((key.trim().to_string(), value));
+    }
+
+    variables
+}
+
+fn resolve_regex() -> Regex {
+    Regex::new(r#"import\.meta\.resolve\(\s*(?:"([^"]+)"|'([^']+)')\s*\)"#).unwrap()
+}
+
+/// The `import.meta.resolve()` calls of `content`, each ranging over the
+/// whole call
+pub(crate) fn parse_resolves(content: &str) -> Vec<Dependency> {
+    resolve_regex()
+        .captures_iter(content)
+        .map(|caps| {
+            let call = caps.get(0).unwrap();
+            let request = caps.get(1).or_else(|| caps.get(2)).unwrap();
+            Dependency::new(request.as_str().to_string(), DependencyType::ImportMeta)
+                .with_range(call.start(), call.end())
+        })
+        .collect()
+}
+
+/// Replace the remaining uses of `import.meta` in `content`. `env` is the
+/// object [`env`] made.
+pub(crate) fn rewrite(content: &str, env: &Value) -> String {
+    // Known variables are inlined, so the code using them can be minified
+    // away; unknown ones are read from the whole object, like in Vite
+    let variable_regex = Regex::new(r"import\.meta\.env\.([A-Za-z_$][\w$]*)").unwrap();
+    let content =
+        variable_regex.replace_all(content, |caps: &regex::Captures| match env.get(&caps[1]) {
+            Some(value) => value.to_string(),
+            None => caps[0].to_string(),
+        });
+
+    let env_object = format!("({})", env);
+    let replacements = [
+        (r"import\.meta\.env\b", env_object.clone()),
+        (r"import\.meta\.url\b", "__webpack_require__.b".to_string()),
+        (r"import\.meta\.hot\b", "undefined".to_string()),
+        (
+            r"import\.meta\b",
+            format!("({{ url: __webpack_require__.b, env: {} }})", env_object),
+        ),
+    ];
+
+    let mut content = content.into_owned();
+    for (pattern, replacement) in replacements {
+        content = Regex::new(pattern)
+            .unwrap()
+            .replace_all(&content, regex::NoExpand(&replacement))
+            .into_owned();
+    }
+    content
+}
+
+/// `file:` URL of `path`, as `import.meta.resolve()` returns on Node.js
+pub(crate) fn file_url(path: &Path) -> String {
+    let path = path.display().to_string().replace('\\', "/");
+    let mut url = String::from("file://");
+    if !path.starts_with('/') {
+        url.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::write(
+            dir.join(".env"),
+            "# Shared\nVITE_API=https://api.example.com # the API\nVITE_NAME='My App'\nSECRET=hunter2\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(".env.production"),
+            "export VITE_API=\"https://prod.example.com\"\nVITE_GREETING=\"a\\nb\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join(".env.development"), "VITE_API=http://localhost\n").unwrap();
+
+        let config = Config {
+            mode: Mode::Production,
+            ..Config::default()
+        };
+        let vars = [
+            ("VITE_NAME".to_string(), "From Env".to_string()),
+            ("HOME".to_string(), "/root".to_string()),
+        ];
+        let env = env(&config, dir, vars);
+        assert_eq!(
+            env.to_string(),
+            r#"{"MODE":"production","DEV":false,"PROD":true,"BASE_URL":"/","SSR":false,"VITE_API":"https://prod.example.com","VITE_GREETING":"a\nb","VITE_NAME":"From Env"}"#
+        );
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let env = serde_json::json!({ "MODE": "development", "DEV": true });
+        let content = "if (import.meta.hot) import.meta.hot.accept();\nconst dev = import.meta.env.DEV, api = import.meta.env.VITE_API;\nconsole.log(import.meta.url, import.meta);\n";
+        assert_eq!(
+            rewrite(content, &env),
+            "if (undefined) undefined.accept();\nconst dev = true, api = ({\"MODE\":\"development\",\"DEV\":true}).VITE_API;\nconsole.log(__webpack_require__.b, ({ url: __webpack_require__.b, env: ({\"MODE\":\"development\",\"DEV\":true}) }));\n"
+        );
+
+        let resolves = parse_resolves("const a = import.meta.resolve('./a.js');");
+        assert_eq!(resolves[0].request, "./a.js");
+        assert_eq!(resolves[0].range.as_ref().unwrap().start, 10);
+
+        assert_eq!(
+            file_url(Path::new("/my app/a#1.js")),
+            "file:///my%20app/a%231.js"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_meta_in_bundle() {
+        use crate::compiler::Compiler;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        std::fs::write(
+            root.join("index.js"),
+            "const helper = import.meta.resolve('./helper.js');\nconsole.log(import.meta.url, import.meta.env.MODE, helper);\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("helper.js"), "").unwrap();
+
+        let config = Config {
+            entry: root.join("index.js").display().to_string(),
+            platform: Platform::Node,
+            ..Config::default()
+        };
+        let result = Compiler::new(config).compile().await.unwrap();
+
+        // Resolving a module doesn't bundle it
+        assert_eq!(result.modules.len(), 1);
+        let bundle = String::from_utf8_lossy(&result.assets["main.js"]);
+        assert!(
+            bundle.contains(
+                "__webpack_require__.b = require(\"url\").pathToFileURL(__filename).href;"
+            )
+        );
+        assert!(bundle.contains(&format!(
+            "const helper = \"{}\";\nconsole.log(__webpack_require__.b, \"development\", helper);",
+            file_url(&root.join("helper.js"))
+        )));
+
+        let config = Config {
+            entry: root.join("index.js").display().to_string(),
+            platform: Platform::Web,
+            ..Config::default()
+        };
+        match Compiler::new(config).compile().await {
+            Err(crate::BundlerError::ParseError { file, message }) => {
+                assert_eq!(file, root.join("index.js").display().to_string());
+                assert!(
+                    message.starts_with("import.meta.resolve('./helper.js') has no URL on the web")
+                );
+            }
+            other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -3,21 +3,28 @@
 //! This crate provides the core functionality for bundling JavaScript/TypeScript
 //! applications, similar to Webpack but implemented in Rust for better performance.
 
+mod asset;
 pub mod bundler;
 pub mod chunk;
 pub mod compiler;
 pub mod config;
+mod css;
+mod css_modules;
 pub mod dependency;
 pub mod error;
+mod glob_import;
+mod hash;
+mod import_meta;
 pub mod loader;
 pub mod module;
 pub mod plugin;
+pub mod sass;
+pub mod target;
+mod transform;
+pub mod tsconfig;
 pub mod types;
-
-pub use bundler::Bundler;
-pub use config::Config;
-pub use error::{BundlerError, Result};
-pub use module::Module;
+pub mod virtual_modules;
+mod wasm;
 
 pub use bundler::Bundler;
 pub use config::{Config, define_config as defineConfig};
@@ -59,7 +66,7 @@ mod tests {
             .with_entry(entry_path.to_str().unwrap())
             .with_output_path(temp_dir.path().join("dist").to_str().unwrap());
 
-        let bundler = Bundler::new(config).await.unwrap();
+        let mut bundler = Bundler::new(config).await.unwrap();
         let result = bundler.run().await;
 
         assert!(result.is_ok());
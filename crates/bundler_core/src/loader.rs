@@ -1,18 +1,135 @@
-use crate::{Module, Result};
+use crate::{
+    Result,
+    config::{DecoratorConfig, JsxConfig, Mode, UseEntry},
+    module::ModuleType,
+    sass::SassLoader,
+    transform::{jsx, jsx_options, transform_with_decorators},
+    tsconfig::CompilerOptions,
+};
 use async_trait::async_trait;
+use bundler_resolver::{Resolution, Resolver};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use swc_core::ecma::parser::{EsSyntax, Syntax, TsSyntax};
+use swc_core::ecma::transforms::typescript;
 
 #[async_trait]
 pub trait Loader: Send + Sync {
-    async fn load(&self, content: &str, path: &Path) -> Result<String>;
+    /// Transform `input`, the previous loader's output or the file as read
+    /// from disk. Loaders that don't change the source map or module type
+    /// should carry them over from `input`.
+    async fn load(&self, input: LoaderOutput, context: &mut LoaderContext) -> Result<LoaderOutput>;
     fn name(&self) -> &str;
+
+    /// Runs first to last before any `load`. Returning output ends the
+    /// pitching phase: this loader and the ones after it are skipped, and the
+    /// output goes through the `load` of the loaders before it.
+    async fn pitch(&self, _context: &mut LoaderContext) -> Result<Option<LoaderOutput>> {
+        Ok(None)
+    }
+}
+
+/// The module a loader chain runs on, and what the loaders report besides
+/// the transformed source
+pub struct LoaderContext {
+    pub resource_path: PathBuf,
+    /// Query the module was requested with, e.g. `?raw`
+    pub resource_query: Option<String>,
+    pub mode: Mode,
+    /// `options` of the current loader's `use` entry, or `null`
+    pub options: Value,
+    pub(crate) dependencies: Vec<PathBuf>,
+    pub(crate) emitted_files: HashMap<String, Vec<u8>>,
+    resolver: Arc<Resolver>,
+}
+
+impl LoaderContext {
+    pub fn new(resource_path: PathBuf, resource_query: Option<String>, mode: Mode) -> Self {
+        Self {
+            resource_path,
+            resource_query,
+            mode,
+            options: Value::Null,
+            dependencies: Vec::new(),
+            emitted_files: HashMap::new(),
+            resolver: Arc::new(Resolver::new()),
+        }
+    }
+
+    /// Resolve requests with `resolver` instead of a default one, so loaders
+    /// see the same aliases and fallbacks as the rest of the build
+    pub fn with_resolver(mut self, resolver: Arc<Resolver>) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Resolve `request` as if the module imported it
+    pub fn resolve(&self, request: &str) -> Result<Resolution> {
+        Ok(self.resolver.resolve_sync(request, &self.resource_path)?)
+    }
+
+    /// Declare that the output also depends on `path`, e.g. a stylesheet the
+    /// loader inlined, so watch mode rebuilds the module when it changes
+    pub fn add_dependency(&mut self, path: impl Into<PathBuf>) {
+        self.dependencies.push(path.into());
+    }
+
+    /// Write `content` to `filename`, relative to the output directory
+    pub fn emit_file(&mut self, filename: impl Into<String>, content: impl Into<Vec<u8>>) {
+        self.emitted_files.insert(filename.into(), content.into());
+    }
+
+    pub fn dependencies(&self) -> &[PathBuf] {
+        &self.dependencies
+    }
+
+    pub fn emitted_files(&self) -> &HashMap<String, Vec<u8>> {
+        &self.emitted_files
+    }
+}
+
+/// Source passed along a loader chain
+#[derive(Debug, Clone, Default)]
+pub struct LoaderOutput {
+    pub content: Vec<u8>,
+    /// Type of the module once transformed, when it differs from the one its
+    /// extension implies, e.g. `JavaScript` for JSON turned into a module
+    pub module_type: Option<ModuleType>,
+}
+
+impl LoaderOutput {
+    pub fn new(content: impl Into<Vec<u8>>) -> Self {
+        Self {
+            content: content.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_module_type(mut self, module_type: ModuleType) -> Self {
+        self.module_type = Some(module_type);
+        self
+    }
+
+    /// The content as text, for loaders that only handle source code
+    pub fn text(&self) -> Result<&str> {
+        std::str::from_utf8(&self.content)
+            .map_err(|e| crate::BundlerError::LoaderError(format!("Expected UTF-8 source: {}", e)))
+    }
 }
 
 pub struct LoaderRegistry {
     loaders: HashMap<String, Box<dyn Loader>>,
 }
 
+impl Default for LoaderRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl LoaderRegistry {
     pub fn new() -> Self {
         let mut registry = Self {
@@ -21,8 +138,12 @@ impl LoaderRegistry {
 
         // Register default loaders
         registry.register("javascript".to_string(), Box::new(JavaScriptLoader));
-        registry.register("typescript".to_string(), Box::new(TypeScriptLoader));
+        registry.register("typescript".to_string(), Box::new(TypeScriptLoader::new()));
         registry.register("json".to_string(), Box::new(JsonLoader));
+        registry.register("yaml".to_string(), Box::new(YamlLoader));
+        registry.register("toml".to_string(), Box::new(TomlLoader));
+        registry.register("raw".to_string(), Box::new(RawLoader));
+        registry.register("sass".to_string(), Box::new(SassLoader));
 
         registry
     }
@@ -31,25 +152,91 @@ impl LoaderRegistry {
         self.loaders.insert(name, loader);
     }
 
-    pub async fn load(&self, loader_name: &str, content: &str, path: &Path) -> Result<String> {
-        match self.loaders.get(loader_name) {
-            Some(loader) => loader.load(content, path).await,
-            None => Err(crate::BundlerError::LoaderError(format!(
-                "Loader '{}' not found",
-                loader_name
-            ))),
+    pub async fn load(
+        &self,
+        loader_name: &str,
+        input: LoaderOutput,
+        context: &mut LoaderContext,
+    ) -> Result<LoaderOutput> {
+        self.get(loader_name)?.load(input, context).await
+    }
+
+    /// Run `input` through a chain of loaders the way webpack does: pitch
+    /// first to last, then load last to first
+    pub async fn run(
+        &self,
+        chain: &[UseEntry],
+        input: LoaderOutput,
+        context: &mut LoaderContext,
+    ) -> Result<LoaderOutput> {
+        let loaders = chain
+            .iter()
+            .map(|entry| Ok((self.get(&entry.loader)?, &entry.options)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut output = input;
+        let mut end = loaders.len();
+        for (index, (loader, options)) in loaders.iter().enumerate() {
+            context.options = (*options).clone();
+            if let Some(pitched) = loader.pitch(context).await? {
+                output = pitched;
+                end = index;
+                break;
+            }
+        }
+
+        for (loader, options) in loaders[..end].iter().rev() {
+            context.options = (*options).clone();
+            output = loader.load(output, context).await?;
         }
+        Ok(output)
+    }
+
+    fn get(&self, loader_name: &str) -> Result<&dyn Loader> {
+        self.loaders
+            .get(loader_name)
+            .map(|loader| loader.as_ref())
+            .ok_or_else(|| {
+                crate::BundlerError::LoaderError(format!("Loader '{}' not found", loader_name))
+            })
     }
 }
 
+/// Bundles scripts as written, except for JSX and decorators: `.jsx` files,
+/// or any file when the `jsx` or `decorators` option is given, are compiled
 pub struct JavaScriptLoader;
 
 #[async_trait]
 impl Loader for JavaScriptLoader {
-    async fn load(&self, content: &str, _path: &Path) -> Result<String> {
-        // For MVP, just return the content as-is
-        // In a real implementation, you'd parse and transform the JS
-        Ok(content.to_string())
+    async fn load(&self, input: LoaderOutput, context: &mut LoaderContext) -> Result<LoaderOutput> {
+        let is_jsx = context
+            .resource_path
+            .extension()
+            .is_some_and(|ext| ext == "jsx");
+        if !is_jsx && context.options["jsx"].is_null() && context.options["decorators"].is_null() {
+            return Ok(input);
+        }
+
+        let options = jsx_options(
+            &loader_option::<JsxConfig>(&context.options, "jsx")?,
+            matches!(context.mode, Mode::Development),
+        )?;
+        let decorators = loader_option::<DecoratorConfig>(&context.options, "decorators")?;
+        let syntax = Syntax::Es(EsSyntax {
+            jsx: true,
+            decorators: true,
+            ..Default::default()
+        });
+        let js_content = transform_with_decorators(
+            input.text()?,
+            &context.resource_path,
+            syntax,
+            &decorators,
+            true,
+            |cx| jsx(cx, options),
+        )?;
+
+        Ok(LoaderOutput::new(js_content).with_module_type(ModuleType::JavaScript))
     }
 
     fn name(&self) -> &str {
@@ -57,24 +244,115 @@ impl Loader for JavaScriptLoader {
     }
 }
 
-pub struct TypeScriptLoader;
+/// The loader option `key`, or its default if not given
+fn loader_option<T: DeserializeOwned + Default>(options: &Value, key: &str) -> Result<T> {
+    if options[key].is_null() {
+        return Ok(T::default());
+    }
+    serde_json::from_value(options[key].clone()).map_err(|e| {
+        crate::BundlerError::ConfigError(format!("Invalid `{}` loader option: {}", key, e))
+    })
+}
+
+/// Transpiles TypeScript, including `.tsx`, to JavaScript with swc.
+///
+/// Compiler options come from the `tsconfig.json` nearest to each file, or
+/// from the file given as the `tsconfig` loader option.
+///
+/// No source map is generated: bundles aren't source-mapped, so there is
+/// nothing to attach one to. Stack traces and devtools point into the
+/// transpiled code.
+#[derive(Default)]
+pub struct TypeScriptLoader {
+    /// Compiler options by tsconfig file or lookup directory
+    tsconfigs: Mutex<HashMap<PathBuf, Arc<CompilerOptions>>>,
+}
+
+impl TypeScriptLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn compiler_options(&self, context: &LoaderContext) -> Result<Arc<CompilerOptions>> {
+        let explicit = context.options["tsconfig"].as_str().map(PathBuf::from);
+        let key = match &explicit {
+            Some(tsconfig) => tsconfig.clone(),
+            None => context
+                .resource_path
+                .parent()
+                .unwrap_or(Path::new("."))
+                .to_path_buf(),
+        };
+
+        let mut tsconfigs = self.tsconfigs.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(options) = tsconfigs.get(&key) {
+            return Ok(options.clone());
+        }
+
+        let options = Arc::new(match explicit {
+            Some(tsconfig) => CompilerOptions::load(&tsconfig)?,
+            None => CompilerOptions::find(&key)?,
+        });
+        tsconfigs.insert(key, options.clone());
+        Ok(options)
+    }
+}
 
 #[async_trait]
 impl Loader for TypeScriptLoader {
-    async fn load(&self, content: &str, _path: &Path) -> Result<String> {
-        // For MVP, strip type annotations (very basic)
-        // In a real implementation, you'd use a proper TS compiler
-        let js_content = content
-            .lines()
-            .map(|line| {
-                // Remove type annotations (very basic regex)
-                let line = regex::Regex::new(r": \w+").unwrap().replace_all(line, "");
-                line.to_string()
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
+    async fn load(&self, input: LoaderOutput, context: &mut LoaderContext) -> Result<LoaderOutput> {
+        let options = self.compiler_options(context)?;
+        let tsx = context
+            .resource_path
+            .extension()
+            .is_some_and(|ext| ext == "tsx");
+        let syntax = Syntax::Typescript(TsSyntax {
+            tsx,
+            decorators: true,
+            ..Default::default()
+        });
+        let config = typescript::Config {
+            verbatim_module_syntax: options.verbatim_module_syntax.unwrap_or(false),
+            native_class_properties: options.define_class_fields(),
+            ..Default::default()
+        };
 
-        Ok(js_content)
+        // The loader's `jsx` option wins over tsconfig's `jsx*` options
+        let jsx_config =
+            loader_option::<JsxConfig>(&context.options, "jsx")?.or(options.jsx_config());
+        let tsx_config = typescript::TsxConfig {
+            pragma: jsx_config.pragma.clone().map(Into::into),
+            pragma_frag: jsx_config.pragma_frag.clone().map(Into::into),
+        };
+        let jsx_options = jsx_options(&jsx_config, matches!(context.mode, Mode::Development))?;
+
+        let decorators = loader_option::<DecoratorConfig>(&context.options, "decorators")?
+            .or(options.decorator_config());
+
+        // `tsx` rather than `typescript` so imports only used by JSX are kept.
+        // Files without JSX pass through the JSX pass unchanged.
+        let js_content = transform_with_decorators(
+            input.text()?,
+            &context.resource_path,
+            syntax,
+            &decorators,
+            options.define_class_fields(),
+            |cx| {
+                (
+                    typescript::tsx(
+                        cx.cm.clone(),
+                        config,
+                        tsx_config,
+                        cx.comments.clone(),
+                        cx.unresolved_mark,
+                        cx.top_level_mark,
+                    ),
+                    jsx(cx, jsx_options),
+                )
+            },
+        )?;
+
+        Ok(LoaderOutput::new(js_content).with_module_type(ModuleType::JavaScript))
     }
 
     fn name(&self) -> &str {
@@ -82,16 +360,563 @@ impl Loader for TypeScriptLoader {
     }
 }
 
+/// Turns JSON into a module whose default export is the parsed value. Keys
+/// of a top-level object that are valid identifiers are also named exports.
+/// `.jsonc` files may have comments and trailing commas, and `.json5` files
+/// anything JSON5 allows.
 pub struct JsonLoader;
 
 #[async_trait]
 impl Loader for JsonLoader {
-    async fn load(&self, content: &str, _path: &Path) -> Result<String> {
-        // Convert JSON to ES module
-        Ok(format!("export default {};", content))
+    async fn load(&self, input: LoaderOutput, context: &mut LoaderContext) -> Result<LoaderOutput> {
+        let path = &context.resource_path;
+        let source = input.text()?;
+        let parse_error =
+            |message: &str, line: usize, column: usize| data_error(path, message, line, column);
+
+        let value: Value = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json5") => json5::from_str(source).map_err(|e| match &e {
+                json5::Error::Message {
+                    msg,
+                    location: Some(location),
+                } => parse_error(msg, location.line, location.column),
+                json5::Error::Message {
+                    msg,
+                    location: None,
+                } => parse_error(msg, 1, 1),
+            })?,
+            extension => {
+                let source = if extension == Some("jsonc") {
+                    crate::tsconfig::strip_jsonc(source).into()
+                } else {
+                    std::borrow::Cow::Borrowed(source)
+                };
+                serde_json::from_str(&source)
+                    .map_err(|e| parse_error(&without_position(&e), e.line(), e.column()))?
+            }
+        };
+
+        Ok(LoaderOutput::new(json_module(&value)).with_module_type(ModuleType::JavaScript))
     }
 
     fn name(&self) -> &str {
         "json"
     }
 }
+
+/// Turns YAML into a module shaped like the one [`JsonLoader`] makes.
+/// Anchors and `<<` merge keys are resolved; mapping keys must be strings.
+pub struct YamlLoader;
+
+#[async_trait]
+impl Loader for YamlLoader {
+    async fn load(&self, input: LoaderOutput, context: &mut LoaderContext) -> Result<LoaderOutput> {
+        let path = &context.resource_path;
+        let parse_error = |e: &dyn std::fmt::Display, location: Option<(usize, usize)>| {
+            let (line, column) = location.unwrap_or((1, 1));
+            data_error(path, &without_position(e), line, column)
+        };
+
+        let mut yaml: serde_yaml::Value = serde_yaml::from_str(input.text()?).map_err(|e| {
+            let location = e
+                .location()
+                .map(|location| (location.line(), location.column()));
+            parse_error(&e, location)
+        })?;
+        yaml.apply_merge().map_err(|e| parse_error(&e, None))?;
+        let value = serde_json::to_value(yaml).map_err(|e| parse_error(&e, None))?;
+
+        Ok(LoaderOutput::new(json_module(&value)).with_module_type(ModuleType::JavaScript))
+    }
+
+    fn name(&self) -> &str {
+        "yaml"
+    }
+}
+
+/// Turns TOML into a module shaped like the one [`JsonLoader`] makes.
+/// Dates and times become strings in RFC 3339 format.
+pub struct TomlLoader;
+
+#[async_trait]
+impl Loader for TomlLoader {
+    async fn load(&self, input: LoaderOutput, context: &mut LoaderContext) -> Result<LoaderOutput> {
+        let source = input.text()?;
+        let table: toml::Table = toml::from_str(source).map_err(|e| {
+            let offset = e.span().map_or(0, |span| span.start);
+            let before = &source[..offset];
+            let line = before.matches('\n').count() + 1;
+            let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+            data_error(&context.resource_path, e.message(), line, column)
+        })?;
+        let value = toml_to_json(toml::Value::Table(table));
+
+        Ok(LoaderOutput::new(json_module(&value)).with_module_type(ModuleType::JavaScript))
+    }
+
+    fn name(&self) -> &str {
+        "toml"
+    }
+}
+
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(string) => Value::String(string),
+        toml::Value::Integer(integer) => Value::from(integer),
+        // JSON has no NaN or infinity
+        toml::Value::Float(float) => serde_json::Number::from_f64(float)
+            .map(Value::Number)
+            .unwrap_or(Value::Null),
+        toml::Value::Boolean(boolean) => Value::Bool(boolean),
+        toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        toml::Value::Array(array) => Value::Array(array.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Exports the file's content as a string, whatever it is
+pub struct RawLoader;
+
+#[async_trait]
+impl Loader for RawLoader {
+    async fn load(
+        &self,
+        input: LoaderOutput,
+        _context: &mut LoaderContext,
+    ) -> Result<LoaderOutput> {
+        let content = serde_json::to_string(input.text()?).unwrap_or_default();
+        Ok(LoaderOutput::new(format!("export default {};", content))
+            .with_module_type(ModuleType::JavaScript))
+    }
+
+    fn name(&self) -> &str {
+        "raw"
+    }
+}
+
+/// A data file that doesn't parse, pointing at where it went wrong
+fn data_error(path: &Path, message: &str, line: usize, column: usize) -> crate::BundlerError {
+    crate::BundlerError::ParseError {
+        file: path.display().to_string(),
+        message: format!("{} at {}:{}", message, line, column),
+    }
+}
+
+/// A parser's error message without its own " at line L column C"
+fn without_position(error: &dyn std::fmt::Display) -> String {
+    let message = error.to_string();
+    message
+        .split(" at line ")
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// The module exporting `value`
+fn json_module(value: &Value) -> String {
+    let Value::Object(object) = value else {
+        return format!("export default {};", value);
+    };
+
+    let mut module = String::new();
+    let mut properties = Vec::new();
+    for (key, value) in object {
+        if is_identifier(key) {
+            module.push_str(&format!("export const {} = {};\n", key, value));
+            properties.push(key.clone());
+        } else {
+            properties.push(format!("{}: {}", Value::from(key.as_str()), value));
+        }
+    }
+    module.push_str(&format!("export default {{ {} }};", properties.join(", ")));
+    module
+}
+
+/// Whether `key` can name a binding
+fn is_identifier(key: &str) -> bool {
+    const RESERVED: &[&str] = &[
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "debugger",
+        "default",
+        "delete",
+        "do",
+        "else",
+        "enum",
+        "export",
+        "extends",
+        "false",
+        "finally",
+        "for",
+        "function",
+        "if",
+        "implements",
+        "import",
+        "in",
+        "instanceof",
+        "interface",
+        "let",
+        "new",
+        "null",
+        "package",
+        "private",
+        "protected",
+        "public",
+        "return",
+        "static",
+        "super",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "typeof",
+        "var",
+        "void",
+        "while",
+        "with",
+        "yield",
+        "arguments",
+        "eval",
+        // Parameters of the function the bundle wraps each module in
+        "module",
+        "exports",
+        "require",
+        "__webpack_require__",
+    ];
+
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        && !RESERVED.contains(&key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Wraps content in `[<prefix>...]`, or pitches `prefix!` if asked to
+    struct WrapLoader;
+
+    #[async_trait]
+    impl Loader for WrapLoader {
+        async fn load(
+            &self,
+            input: LoaderOutput,
+            context: &mut LoaderContext,
+        ) -> Result<LoaderOutput> {
+            let prefix = context.options["prefix"].as_str().unwrap().to_string();
+            context.add_dependency(format!("{}.dep", prefix));
+            context.emit_file(format!("{}.txt", prefix), prefix.clone());
+            Ok(LoaderOutput::new(format!("[{}{}]", prefix, input.text()?)))
+        }
+
+        fn name(&self) -> &str {
+            "wrap"
+        }
+
+        async fn pitch(&self, context: &mut LoaderContext) -> Result<Option<LoaderOutput>> {
+            let options = &context.options;
+            Ok(options["pitch"].as_bool().unwrap_or(false).then(|| {
+                LoaderOutput::new(format!("{}!", options["prefix"].as_str().unwrap()))
+                    .with_module_type(ModuleType::Css)
+            }))
+        }
+    }
+
+    async fn run(chain: &[UseEntry]) -> (Result<LoaderOutput>, LoaderContext) {
+        let mut registry = LoaderRegistry::new();
+        registry.register("wrap".to_string(), Box::new(WrapLoader));
+        let mut context = LoaderContext::new(PathBuf::from("a.css"), None, Mode::Development);
+        let output = registry
+            .run(chain, LoaderOutput::new("src"), &mut context)
+            .await;
+        (output, context)
+    }
+
+    #[tokio::test]
+    async fn test_chain_order_and_pitch() {
+        let wrap = |options| UseEntry::new("wrap").with_options(options);
+
+        let chain = [
+            wrap(json!({ "prefix": "a" })),
+            wrap(json!({ "prefix": "b" })),
+        ];
+        let (output, context) = run(&chain).await;
+        assert_eq!(output.unwrap().content, b"[a[bsrc]]");
+        assert_eq!(
+            context.dependencies(),
+            [PathBuf::from("b.dep"), PathBuf::from("a.dep")]
+        );
+        assert_eq!(context.emitted_files()["a.txt"], b"a");
+
+        let chain = [
+            wrap(json!({ "prefix": "a" })),
+            wrap(json!({ "prefix": "b", "pitch": true })),
+            wrap(json!({ "prefix": "c" })),
+        ];
+        let output = run(&chain).await.0.unwrap();
+        assert_eq!(output.content, b"[ab!]");
+        assert!(output.module_type.is_none());
+
+        assert!(run(&[UseEntry::new("missing")]).await.0.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_typescript_loader_transpiles() {
+        let source = r#"
+            import type { Props } from './props';
+            import { helper } from './helper';
+            interface Point { x: number; y: number }
+            enum Color { Red, Green }
+            class User {
+                constructor(private name: string) {}
+                greet<T>(value: T): string {
+                    return helper(this.name, value as unknown as Props);
+                }
+            }
+            export const point: Point = { x: 1, y: Color.Green ? 2 : 3 };
+        "#;
+
+        // Away from any tsconfig.json of the machine running the tests
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut context = LoaderContext::new(temp_dir.path().join("a.ts"), None, Mode::Development);
+        let output = TypeScriptLoader::new()
+            .load(LoaderOutput::new(source), &mut context)
+            .await
+            .unwrap();
+        let code = output.text().unwrap();
+
+        assert!(!code.contains("./props"));
+        assert!(code.contains("./helper"));
+        assert!(!code.contains("interface"));
+        assert!(code.contains("this.name = name"));
+        assert!(code.contains("Color[Color[\"Red\"] = 0] = \"Red\""));
+        assert!(code.contains("x: 1"));
+        assert!(matches!(output.module_type, Some(ModuleType::JavaScript)));
+    }
+
+    #[tokio::test]
+    async fn test_jsx_runtimes() {
+        let source = "export const App = () => <><h1 className=\"title\">Hi</h1></>;";
+
+        let mut context = LoaderContext::new(PathBuf::from("/a.jsx"), None, Mode::Production);
+        let output = JavaScriptLoader
+            .load(LoaderOutput::new(source), &mut context)
+            .await
+            .unwrap();
+        let code = output.text().unwrap();
+        assert!(code.contains("react/jsx-runtime"));
+        assert!(!code.contains("<h1"));
+
+        let mut context = LoaderContext::new(PathBuf::from("/a.jsx"), None, Mode::Development);
+        context.options =
+            json!({ "jsx": { "runtime": "Classic", "pragma": "h", "pragma_frag": "Fragment" } });
+        let output = JavaScriptLoader
+            .load(LoaderOutput::new(source), &mut context)
+            .await
+            .unwrap();
+        let code = output.text().unwrap();
+        assert!(code.contains("h(Fragment"));
+        assert!(code.contains("__source"));
+
+        let mut context = LoaderContext::new(PathBuf::from("/a.tsx"), None, Mode::Production);
+        context.options = json!({ "jsx": { "importSource": "preact" } });
+        let output = TypeScriptLoader::new()
+            .load(
+                LoaderOutput::new("export const App = (props: { n: number }) => <b>{props.n}</b>;"),
+                &mut context,
+            )
+            .await
+            .unwrap();
+        assert!(output.text().unwrap().contains("preact/jsx-runtime"));
+    }
+
+    #[tokio::test]
+    async fn test_decorators() {
+        let source = r#"
+            import { Injectable } from './di';
+            import { Logger } from './logger';
+            @Injectable()
+            export class Service {
+                constructor(private logger: Logger) {}
+            }
+        "#;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("tsconfig.json"),
+            r#"{ "compilerOptions": { "experimentalDecorators": true, "emitDecoratorMetadata": true } }"#,
+        )
+        .unwrap();
+        let mut context =
+            LoaderContext::new(temp_dir.path().join("service.ts"), None, Mode::Production);
+        let output = TypeScriptLoader::new()
+            .load(LoaderOutput::new(source), &mut context)
+            .await
+            .unwrap();
+        let code = output.text().unwrap();
+        assert!(!code.contains("@Injectable"));
+        assert!(code.contains("design:paramtypes"));
+        // Logger is referenced by the metadata, so its import stays
+        assert!(code.contains("./logger"));
+
+        let mut context = LoaderContext::new(PathBuf::from("/a.js"), None, Mode::Production);
+        context.options = json!({ "decorators": { "version": "Standard" } });
+        let output = JavaScriptLoader
+            .load(
+                LoaderOutput::new("@sealed class A { @bound method() {} }"),
+                &mut context,
+            )
+            .await
+            .unwrap();
+        assert!(!output.text().unwrap().contains("@sealed"));
+    }
+
+    #[tokio::test]
+    async fn test_json_loader_rejects_binary() {
+        let mut context = LoaderContext::new(PathBuf::from("a.json"), None, Mode::Production);
+        let output = JsonLoader
+            .load(LoaderOutput::new(vec![0xff, 0xfe]), &mut context)
+            .await;
+        assert!(output.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_json_loader_formats() {
+        let load = |path: &str, source: &str| {
+            let mut context = LoaderContext::new(PathBuf::from(path), None, Mode::Production);
+            let source = source.to_string();
+            async move {
+                JsonLoader
+                    .load(LoaderOutput::new(source), &mut context)
+                    .await
+                    .map(|output| String::from_utf8(output.content).unwrap())
+            }
+        };
+
+        assert_eq!(
+            load(
+                "a.json",
+                r#"{ "name": "app", "default": 1, "my-key": [true] }"#
+            )
+            .await
+            .unwrap(),
+            "export const name = \"app\";\nexport default { name, \"default\": 1, \"my-key\": [true] };"
+        );
+        assert_eq!(
+            load("a.json", "[1, 2]").await.unwrap(),
+            "export default [1,2];"
+        );
+        assert_eq!(
+            load("a.jsonc", "{\n  // Port\n  \"port\": 80,\n}")
+                .await
+                .unwrap(),
+            "export const port = 80;\nexport default { port };"
+        );
+        assert_eq!(
+            load("a.json5", "{ port: 0x50, host: 'localhost', }")
+                .await
+                .unwrap(),
+            "export const port = 80;\nexport const host = \"localhost\";\nexport default { port, host };"
+        );
+        assert_eq!(
+            load(
+                "package.json",
+                r#"{ "name": "pkg", "module": "index.mjs", "exports": { ".": "./index.js" }, "require": null, "__webpack_require__": 1 }"#
+            )
+            .await
+            .unwrap(),
+            "export const name = \"pkg\";\nexport default { name, \"module\": \"index.mjs\", \"exports\": {\".\":\"./index.js\"}, \"require\": null, \"__webpack_require__\": 1 };"
+        );
+
+        match load("a.json", "{\n  \"a\": 1,\n  \"b\" 2\n}").await {
+            Err(crate::BundlerError::ParseError { file, message }) => {
+                assert_eq!(file, "a.json");
+                assert_eq!(message, "expected `:` at 3:7");
+            }
+            other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_yaml_toml_and_raw_loaders() {
+        let load = |loader: Box<dyn Loader>, path: &str, source: &str| {
+            let mut context = LoaderContext::new(PathBuf::from(path), None, Mode::Production);
+            let source = source.to_string();
+            async move {
+                loader
+                    .load(LoaderOutput::new(source), &mut context)
+                    .await
+                    .map(|output| String::from_utf8(output.content).unwrap())
+            }
+        };
+
+        let yaml = "base: &base\n  greeting: Hello\nen:\n  <<: *base\n  farewell: Bye\n";
+        assert_eq!(
+            load(Box::new(YamlLoader), "en.yaml", yaml).await.unwrap(),
+            "export const base = {\"greeting\":\"Hello\"};\nexport const en = {\"farewell\":\"Bye\",\"greeting\":\"Hello\"};\nexport default { base, en };"
+        );
+        match load(Box::new(YamlLoader), "a.yml", "a: [1, 2\nb: 3\n").await {
+            Err(crate::BundlerError::ParseError { file, message }) => {
+                assert_eq!(file, "a.yml");
+                assert!(message.ends_with(" at 2:2"), "{}", message);
+            }
+            other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+        }
+        assert_eq!(
+            load(
+                Box::new(YamlLoader),
+                "a.yaml",
+                "module: esm\nexports: [a]\n"
+            )
+            .await
+            .unwrap(),
+            "export default { \"module\": \"esm\", \"exports\": [\"a\"] };"
+        );
+
+        let toml = "title = \"flags\"\nreleased = 2024-05-01\n\n[features]\nbeta = true\n";
+        assert_eq!(
+            load(Box::new(TomlLoader), "flags.toml", toml)
+                .await
+                .unwrap(),
+            "export const title = \"flags\";\nexport const released = \"2024-05-01\";\nexport const features = {\"beta\":true};\nexport default { title, released, features };"
+        );
+        assert_eq!(
+            load(
+                Box::new(TomlLoader),
+                "a.toml",
+                "module = 1\n[exports]\nx = 2\n"
+            )
+            .await
+            .unwrap(),
+            "export default { \"module\": 1, \"exports\": {\"x\":2} };"
+        );
+        match load(Box::new(TomlLoader), "a.toml", "a = 1\nb = \n").await {
+            Err(crate::BundlerError::ParseError { message, .. }) => {
+                assert!(message.ends_with(" at 2:5"), "{}", message);
+            }
+            other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+        }
+
+        assert_eq!(
+            load(Box::new(RawLoader), "a.txt", "line \"1\"\n")
+                .await
+                .unwrap(),
+            r#"export default "line \"1\"\n";"#
+        );
+    }
+}
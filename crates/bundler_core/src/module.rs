@@ -1,7 +1,7 @@
 use crate::{Result, dependency::Dependency};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Module {
@@ -11,6 +11,16 @@ pub struct Module {
     pub dependencies: Vec<Dependency>,
     pub source_map: Option<String>,
     pub module_type: ModuleType,
+    /// Resource query the module was requested with, e.g. `?raw`
+    pub resource_query: Option<String>,
+    /// Fragment the module was requested with, e.g. `#section`
+    pub resource_fragment: Option<String>,
+    /// Files besides `path` that loaders read to build this module
+    pub build_dependencies: Vec<PathBuf>,
+    /// Files loaders emitted for this module, keyed by output filename
+    pub emitted_files: HashMap<String, Vec<u8>>,
+    /// Class names a CSS module exports, keyed by the names in its source
+    pub css_classes: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +30,10 @@ pub enum ModuleType {
     Json,
     Css,
     Asset,
+    /// A `.wasm` binary, bundled as a script that instantiates it
+    WebAssembly,
+    /// Provided by the runtime environment rather than bundled, e.g. `node:fs`
+    External,
 }
 
 impl Module {
@@ -33,9 +47,24 @@ impl Module {
             dependencies: Vec::new(),
             source_map: None,
             module_type,
+            resource_query: None,
+            resource_fragment: None,
+            build_dependencies: Vec::new(),
+            emitted_files: HashMap::new(),
+            css_classes: BTreeMap::new(),
         }
     }
 
+    pub fn with_resource_query(mut self, query: Option<String>) -> Self {
+        self.resource_query = query;
+        self
+    }
+
+    pub fn with_resource_fragment(mut self, fragment: Option<String>) -> Self {
+        self.resource_fragment = fragment;
+        self
+    }
+
     pub fn add_dependency(&mut self, dependency: Dependency) {
         self.dependencies.push(dependency);
     }
@@ -47,12 +76,13 @@ impl Module {
             .collect()
     }
 
-    fn determine_type(path: &PathBuf) -> ModuleType {
+    fn determine_type(path: &Path) -> ModuleType {
         match path.extension().and_then(|ext| ext.to_str()) {
-            Some("js") => ModuleType::JavaScript,
-            Some("ts") => ModuleType::TypeScript,
+            Some("js" | "jsx" | "mjs" | "cjs") => ModuleType::JavaScript,
+            Some("ts" | "tsx" | "mts" | "cts") => ModuleType::TypeScript,
             Some("json") => ModuleType::Json,
             Some("css") => ModuleType::Css,
+            Some("wasm") => ModuleType::WebAssembly,
             _ => ModuleType::Asset,
         }
     }
@@ -68,6 +98,12 @@ pub struct ModuleGraph {
     dependencies: Vec<(String, String)>, // (from_id, to_id)
 }
 
+impl Default for ModuleGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ModuleGraph {
     pub fn new() -> Self {
         Self {
@@ -98,12 +134,12 @@ impl ModuleGraph {
         let mut rec_stack = HashSet::new();
 
         for module in &self.modules {
-            if !visited.contains(&module.id) {
-                if self.has_cycle(&module.id, &mut visited, &mut rec_stack)? {
-                    return Err(crate::BundlerError::CircularDependency {
-                        modules: rec_stack.into_iter().collect(),
-                    });
-                }
+            if !visited.contains(&module.id)
+                && self.has_cycle(&module.id, &mut visited, &mut rec_stack)?
+            {
+                return Err(crate::BundlerError::CircularDependency {
+                    modules: rec_stack.into_iter().collect(),
+                });
             }
         }
 
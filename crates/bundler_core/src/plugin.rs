@@ -6,6 +6,12 @@ use std::collections::HashMap;
 pub trait Plugin: Send + Sync {
     async fn apply(&self, context: &mut PluginContext) -> Result<()>;
     fn name(&self) -> &str;
+
+    /// Supply the content of the virtual module `id` (`virtual:routes`,
+    /// `\0build-info`, ...), or `None` to leave it to other plugins
+    async fn load_virtual(&self, _id: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
 }
 
 pub struct PluginContext {
@@ -28,6 +34,12 @@ pub struct PluginManager {
     plugins: Vec<Box<dyn Plugin>>,
 }
 
+impl Default for PluginManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PluginManager {
     pub fn new() -> Self {
         Self {
@@ -45,10 +57,21 @@ impl PluginManager {
         }
         Ok(())
     }
+
+    /// Content of the virtual module `id` from the first plugin providing it
+    pub async fn load_virtual(&self, id: &str) -> Result<Option<String>> {
+        for plugin in &self.plugins {
+            if let Some(content) = plugin.load_virtual(id).await? {
+                return Ok(Some(content));
+            }
+        }
+        Ok(None)
+    }
 }
 
 // Example plugin for generating HTML
 pub struct HtmlPlugin {
+    #[allow(dead_code)]
     template: String,
 }
 
@@ -61,8 +84,7 @@ impl HtmlPlugin {
 #[async_trait]
 impl Plugin for HtmlPlugin {
     async fn apply(&self, context: &mut PluginContext) -> Result<()> {
-        let html_content = format!(
-            r#"<!DOCTYPE html>
+        let html_content = r#"<!DOCTYPE html>
 <html>
 <head>
     <title>Bundled App</title>
@@ -71,8 +93,7 @@ impl Plugin for HtmlPlugin {
     <div id="root"></div>
     <script src="bundle.js"></script>
 </body>
-</html>"#
-        );
+</html>"#;
 
         let html_path = format!("{}/index.html", context.output_path);
         tokio::fs::write(html_path, html_content).await?;
@@ -0,0 +1,197 @@
+//! Sass and SCSS, compiled with grass
+//!
+//! grass looks files up through [`grass::Fs`]. Relative `@use`s and
+//! `@import`s are read from disk; anything else is tried under a made-up
+//! load path, which the file system hands to the bundle's resolver, so
+//! packages in `node_modules` and `resolve.alias` work as they do for
+//! scripts.
+
+use crate::{
+    BundlerError, Result,
+    loader::{Loader, LoaderContext, LoaderOutput},
+    module::ModuleType,
+};
+use async_trait::async_trait;
+use bundler_resolver::Resolution;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Load path under which requests go through the resolver
+const RESOLVED: &str = "/__bundler_resolve__";
+
+/// Compiles `.scss` and `.sass` files to CSS. Every file read besides the
+/// module itself becomes a dependency of the module.
+///
+/// No source map is produced: grass has no source map output, so devtools
+/// show the compiled CSS.
+pub struct SassLoader;
+
+#[async_trait]
+impl Loader for SassLoader {
+    async fn load(&self, input: LoaderOutput, context: &mut LoaderContext) -> Result<LoaderOutput> {
+        let syntax = match context.resource_path.extension() {
+            Some(extension) if extension == "sass" => grass::InputSyntax::Sass,
+            _ => grass::InputSyntax::Scss,
+        };
+        let fs = ResolverFs {
+            context,
+            source: &input.content,
+            read: Mutex::new(Vec::new()),
+        };
+        let options = grass::Options::default()
+            .fs(&fs)
+            .load_path(RESOLVED)
+            .input_syntax(syntax)
+            .style(grass::OutputStyle::Expanded);
+
+        let css = grass::from_path(&context.resource_path, &options).map_err(|e| {
+            BundlerError::ParseError {
+                file: context.resource_path.display().to_string(),
+                message: e.to_string(),
+            }
+        })?;
+        let read = fs.read.into_inner().unwrap_or_else(|e| e.into_inner());
+
+        for path in read {
+            context.add_dependency(path);
+        }
+
+        Ok(LoaderOutput::new(css).with_module_type(ModuleType::Css))
+    }
+
+    fn name(&self) -> &str {
+        "sass"
+    }
+}
+
+/// The file system grass compiles a module against
+struct ResolverFs<'a> {
+    context: &'a LoaderContext,
+    /// The module's source, as the loaders before this one left it
+    source: &'a [u8],
+    /// Every other file read
+    read: Mutex<Vec<PathBuf>>,
+}
+
+impl ResolverFs<'_> {
+    /// Where `path`, as grass asked for it, is on disk
+    fn locate(&self, path: &Path) -> Option<PathBuf> {
+        let Ok(request) = path.strip_prefix(RESOLVED) else {
+            return Some(path.to_path_buf());
+        };
+        let request = request.to_string_lossy().replace('\\', "/");
+        // `~` marks packages in older stylesheets
+        let request = request.strip_prefix('~').unwrap_or(&request);
+        match self.context.resolve(request) {
+            Ok(Resolution::File(path)) => Some(path),
+            _ => None,
+        }
+    }
+}
+
+// grass wants file systems to be `Debug`
+impl std::fmt::Debug for ResolverFs<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResolverFs")
+            .field("resource_path", &self.context.resource_path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl grass::Fs for ResolverFs<'_> {
+    fn is_dir(&self, path: &Path) -> bool {
+        self.locate(path)
+            .is_some_and(|path| bundler_resolver::fs::is_dir(&path))
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path == self.context.resource_path
+            || self
+                .locate(path)
+                .is_some_and(|path| bundler_resolver::fs::is_file(&path))
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        if path == self.context.resource_path {
+            return Ok(self.source.to_vec());
+        }
+
+        let located = self
+            .locate(path)
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))?;
+        let content = bundler_resolver::fs::read(&located)?;
+        self.read
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(located);
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Mode;
+    use bundler_resolver::Resolver;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_use_through_resolver() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("node_modules/design/scss")).unwrap();
+        fs::create_dir_all(root.join("src/styles")).unwrap();
+        fs::write(
+            root.join("node_modules/design/scss/_tokens.scss"),
+            "$primary: #336699 !default;",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/styles/_mixins.scss"),
+            "@mixin card { padding: 8px; }",
+        )
+        .unwrap();
+        let entry = root.join("src/app.scss");
+        let source = "@use \"@ds/tokens\";\n@import \"styles/mixins\";\n.app { @include card; color: tokens.$primary; }\n";
+        fs::write(&entry, source).unwrap();
+
+        let resolver = Resolver::new().with_alias(HashMap::from([(
+            "@ds".to_string(),
+            root.join("node_modules/design/scss").display().to_string(),
+        )]));
+        let mut context = LoaderContext::new(entry.clone(), None, Mode::Development)
+            .with_resolver(Arc::new(resolver));
+
+        let fs = ResolverFs {
+            context: &context,
+            source: source.as_bytes(),
+            read: Mutex::new(Vec::new()),
+        };
+        assert!(grass::Fs::is_file(
+            &fs,
+            &Path::new(RESOLVED).join("@ds/_tokens.scss")
+        ));
+        assert!(!grass::Fs::is_file(
+            &fs,
+            &Path::new(RESOLVED).join("@ds/tokens.scss")
+        ));
+
+        let output = SassLoader
+            .load(LoaderOutput::new(source), &mut context)
+            .await
+            .unwrap();
+        let css = output.text().unwrap();
+        assert!(css.contains("padding: 8px;"));
+        assert!(css.contains("color: #336699;"));
+        assert!(matches!(output.module_type, Some(ModuleType::Css)));
+
+        let mut dependencies = context.dependencies().to_vec();
+        dependencies.sort();
+        assert_eq!(dependencies.len(), 2);
+        assert!(dependencies[0].ends_with("node_modules/design/scss/_tokens.scss"));
+        assert!(dependencies[1].ends_with("src/styles/_mixins.scss"));
+    }
+}
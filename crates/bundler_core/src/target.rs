@@ -0,0 +1,239 @@
+//! Which environments the bundle has to run in
+//!
+//! The compiler lowers syntax for a browserslist query. It comes from the
+//! `target` setting, which may also name an ES version, or else from the
+//! project's `browserslist` field in `package.json` or `.browserslistrc`.
+
+use crate::config::Mode;
+use std::path::Path;
+
+/// Browsers that first supported each ES version in full. Lowering for an ES
+/// version lowers for these.
+const ES_VERSIONS: &[(&str, &str)] = &[
+    ("es5", "ie 11"),
+    (
+        "es2015",
+        "chrome 51, edge 15, firefox 54, safari 10, opera 38",
+    ),
+    (
+        "es2016",
+        "chrome 52, edge 15, firefox 52, safari 10.1, opera 39",
+    ),
+    (
+        "es2017",
+        "chrome 58, edge 16, firefox 53, safari 11, opera 45",
+    ),
+    (
+        "es2018",
+        "chrome 64, edge 79, firefox 78, safari 12, opera 51",
+    ),
+    (
+        "es2019",
+        "chrome 73, edge 79, firefox 64, safari 12.1, opera 60",
+    ),
+    (
+        "es2020",
+        "chrome 80, edge 80, firefox 80, safari 14.1, opera 67",
+    ),
+    (
+        "es2021",
+        "chrome 85, edge 85, firefox 79, safari 14.1, opera 71",
+    ),
+    (
+        "es2022",
+        "chrome 94, edge 94, firefox 93, safari 16.4, opera 80",
+    ),
+];
+
+/// The browserslist query to lower syntax for, or `None` to leave syntax as
+/// written. `target` wins over the project files found in `root`.
+pub fn browserslist_query(target: Option<&str>, root: &Path, mode: &Mode) -> Option<String> {
+    match target {
+        Some(target) => from_target(target),
+        None => from_package_json(root, mode).or_else(|| from_browserslistrc(root, mode)),
+    }
+}
+
+/// `target` as a query: an ES version maps to the browsers listed for it,
+/// anything else already is one
+fn from_target(target: &str) -> Option<String> {
+    let version = target.trim().to_ascii_lowercase();
+    if version == "esnext" {
+        return None;
+    }
+    let version = match version.as_str() {
+        "es6" => "es2015",
+        version => version,
+    };
+
+    Some(
+        ES_VERSIONS
+            .iter()
+            .find(|(name, _)| *name == version)
+            .map_or(target, |(_, query)| query)
+            .to_string(),
+    )
+}
+
+/// The `browserslist` field of `package.json`: a query, a list of queries, or
+/// lists keyed by environment
+fn from_package_json(root: &Path, mode: &Mode) -> Option<String> {
+    let content = std::fs::read_to_string(root.join("package.json")).ok()?;
+    let package: serde_json::Value = match serde_json::from_str(&content) {
+        Ok(package) => package,
+        Err(e) => {
+            tracing::warn!("Ignoring invalid package.json: {}", e);
+            return None;
+        }
+    };
+
+    let browserslist = &package["browserslist"];
+    let queries = match browserslist {
+        serde_json::Value::Object(environments) => environments
+            .get(environment(mode))
+            .or_else(|| environments.get("defaults"))?,
+        queries => queries,
+    };
+    match queries {
+        serde_json::Value::String(query) => Some(query.clone()),
+        serde_json::Value::Array(queries) => join(queries.iter().filter_map(|q| q.as_str())),
+        _ => None,
+    }
+}
+
+/// `.browserslistrc`: one query per line and `#` comments. A section like
+/// `[production]` replaces the queries before any section in that
+/// environment.
+fn from_browserslistrc(root: &Path, mode: &Mode) -> Option<String> {
+    let content = std::fs::read_to_string(root.join(".browserslistrc")).ok()?;
+    let mut defaults = Vec::new();
+    let mut environment_queries = None;
+    // `None` before the first section, otherwise whether it is ours
+    let mut in_environment = None;
+
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if let Some(sections) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let matches = sections.split_whitespace().any(|s| s == environment(mode));
+            if matches {
+                environment_queries.get_or_insert_with(Vec::new);
+            }
+            in_environment = Some(matches);
+        } else if !line.is_empty() {
+            match in_environment {
+                None => defaults.push(line),
+                Some(true) => environment_queries.get_or_insert_with(Vec::new).push(line),
+                Some(false) => {}
+            }
+        }
+    }
+
+    join(environment_queries.unwrap_or(defaults).into_iter())
+}
+
+fn environment(mode: &Mode) -> &'static str {
+    match mode {
+        Mode::Development => "development",
+        Mode::Production => "production",
+    }
+}
+
+fn join<'a>(queries: impl Iterator<Item = &'a str>) -> Option<String> {
+    let query = queries.collect::<Vec<_>>().join(", ");
+    (!query.is_empty()).then_some(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_browserslist_query_sources() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let production = Mode::Production;
+
+        assert_eq!(browserslist_query(None, root, &production), None);
+        assert_eq!(
+            browserslist_query(Some("ES2017"), root, &production).as_deref(),
+            Some("chrome 58, edge 16, firefox 53, safari 11, opera 45")
+        );
+        assert_eq!(browserslist_query(Some("esnext"), root, &production), None);
+        assert_eq!(
+            browserslist_query(Some("safari 9"), root, &production).as_deref(),
+            Some("safari 9")
+        );
+
+        fs::write(
+            root.join(".browserslistrc"),
+            "# Embedded devices\nchrome 49\n\n[development]\nlast 1 chrome version\n",
+        )
+        .unwrap();
+        assert_eq!(
+            browserslist_query(None, root, &production).as_deref(),
+            Some("chrome 49")
+        );
+        assert_eq!(
+            browserslist_query(None, root, &Mode::Development).as_deref(),
+            Some("last 1 chrome version")
+        );
+
+        fs::write(
+            root.join("package.json"),
+            r#"{ "browserslist": { "production": ["> 1%", "not dead"] } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            browserslist_query(None, root, &production).as_deref(),
+            Some("> 1%, not dead")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lower_for_target() {
+        use crate::{Config, compiler::Compiler};
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::write(
+            root.join("index.js"),
+            "class Counter { count = 0; }\nconsole.log(new Counter()?.count);\n",
+        )
+        .unwrap();
+
+        let compile = |target: Option<&str>| {
+            let config = Config {
+                context: Some(root.display().to_string()),
+                entry: root.join("index.js").display().to_string(),
+                target: target.map(str::to_string),
+                ..Config::default()
+            };
+            async move {
+                let result = Compiler::new(config).compile().await.unwrap();
+                String::from_utf8_lossy(&result.assets["main.js"]).into_owned()
+            }
+        };
+
+        // Chrome 60 has neither class fields nor optional chaining
+        let bundle = compile(Some("chrome 60")).await;
+        assert!(!bundle.contains("?."));
+        assert!(!bundle.contains("count = 0;\n}"));
+        assert!(bundle.contains("_define_property"));
+
+        // Without a target, the project root's browserslist applies
+        assert!(compile(None).await.contains("?.count"));
+        fs::write(root.join(".browserslistrc"), "chrome 60\n").unwrap();
+        assert!(!compile(None).await.contains("?."));
+
+        let lowered = crate::transform::lower(
+            "export const flat = [[1], [2]].flat();\n",
+            &root.join("flat.js"),
+            "chrome 60",
+            Some("3.38"),
+        )
+        .unwrap();
+        assert!(lowered.contains("core-js/modules/es.array.flat.js"));
+    }
+}
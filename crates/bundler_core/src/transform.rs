@@ -0,0 +1,206 @@
+//! Parsing, transforming and printing scripts with swc
+//!
+//! Loaders describe the passes they need; this module owns the source map,
+//! comments and marks those passes share and turns the result back into code.
+
+use crate::{
+    BundlerError, Result,
+    config::{DecoratorConfig, DecoratorVersion, JsxConfig, JsxRuntime},
+};
+use std::path::Path;
+use swc_core::common::{
+    FileName, GLOBALS, Globals, Mark, SourceMap, Spanned, comments::SingleThreadedComments,
+    sync::Lrc,
+};
+use swc_core::ecma::ast::{EsVersion, Pass, Program};
+use swc_core::ecma::codegen::to_code_default;
+use swc_core::ecma::parser::{EsSyntax, Syntax, error::Error as ParseError, parse_file_as_program};
+use swc_core::ecma::preset_env;
+use swc_core::ecma::transforms::base::{
+    assumptions::Assumptions,
+    fixer::fixer,
+    helpers::{HELPERS, Helpers, inject_helpers},
+    hygiene::hygiene,
+    resolver,
+};
+use swc_core::ecma::transforms::proposal::{decorator_2022_03::decorator_2022_03, decorators};
+use swc_core::ecma::transforms::react;
+
+/// What the passes of one [`transform`] call share
+pub(crate) struct TransformContext {
+    pub cm: Lrc<SourceMap>,
+    /// Shares its storage with the comments the program was parsed with
+    pub comments: SingleThreadedComments,
+    /// Mark of identifiers that aren't declared anywhere, i.e. globals
+    pub unresolved_mark: Mark,
+    /// Mark of identifiers declared at the top level of the module
+    pub top_level_mark: Mark,
+}
+
+/// Parse `source` with `syntax`, run the pass `passes` builds over it and
+/// print the result as JavaScript
+pub(crate) fn transform<P, F>(
+    source: &str,
+    path: &Path,
+    syntax: Syntax,
+    passes: F,
+) -> Result<String>
+where
+    P: Pass,
+    F: FnOnce(&TransformContext) -> P,
+{
+    let cm: Lrc<SourceMap> = Default::default();
+    let comments = SingleThreadedComments::default();
+    let fm = cm.new_source_file(
+        FileName::Real(path.to_path_buf()).into(),
+        source.to_string(),
+    );
+
+    // Helpers used by lowered syntax are inlined into the module
+    GLOBALS.set(&Globals::new(), || {
+        HELPERS.set(&Helpers::new(false), || {
+            let mut errors = Vec::new();
+            let program = parse_file_as_program(
+                &fm,
+                syntax,
+                EsVersion::latest(),
+                Some(&comments),
+                &mut errors,
+            )
+            .map_err(|e| parse_error(&cm, path, e))?;
+            // Recoverable errors are still errors in source we are asked to build
+            if let Some(e) = errors.into_iter().next() {
+                return Err(parse_error(&cm, path, e));
+            }
+
+            let context = TransformContext {
+                cm: cm.clone(),
+                comments: comments.clone(),
+                unresolved_mark: Mark::new(),
+                top_level_mark: Mark::new(),
+            };
+
+            let program: Program = program
+                .apply(resolver(
+                    context.unresolved_mark,
+                    context.top_level_mark,
+                    syntax.typescript(),
+                ))
+                .apply(passes(&context))
+                .apply(inject_helpers(context.unresolved_mark))
+                .apply(hygiene())
+                .apply(fixer(Some(&comments)));
+
+            Ok(to_code_default(cm.clone(), Some(&comments), &program))
+        })
+    })
+}
+
+/// Like [`transform`], compiling decorators as `decorators` says before
+/// running `passes`. Without a version, decorators are left as written.
+pub(crate) fn transform_with_decorators<P, F>(
+    source: &str,
+    path: &Path,
+    syntax: Syntax,
+    decorators: &DecoratorConfig,
+    define_class_fields: bool,
+    passes: F,
+) -> Result<String>
+where
+    P: Pass,
+    F: FnOnce(&TransformContext) -> P,
+{
+    match decorators.version {
+        None => transform(source, path, syntax, passes),
+        Some(DecoratorVersion::Legacy) => {
+            let config = decorators::Config {
+                legacy: true,
+                emit_metadata: decorators.emit_metadata.unwrap_or(false),
+                use_define_for_class_fields: define_class_fields,
+            };
+            // Metadata needs the type annotations, so this runs before
+            // TypeScript is stripped
+            transform(source, path, syntax, |cx| {
+                (decorators::decorators(config), passes(cx))
+            })
+        }
+        Some(DecoratorVersion::Standard) => {
+            transform(source, path, syntax, |cx| (decorator_2022_03(), passes(cx)))
+        }
+    }
+}
+
+/// swc options for compiling JSX as `jsx` describes, with debug info by
+/// default when `development`
+pub(crate) fn jsx_options(jsx: &JsxConfig, development: bool) -> Result<react::Options> {
+    // Built from the `jsc.transform.react` shape of `.swcrc`
+    let runtime = match jsx.runtime.unwrap_or(JsxRuntime::Automatic) {
+        JsxRuntime::Classic => "classic",
+        JsxRuntime::Automatic => "automatic",
+    };
+    serde_json::from_value(serde_json::json!({
+        "runtime": runtime,
+        "importSource": jsx.import_source.as_deref().unwrap_or("react"),
+        "pragma": jsx.pragma.as_deref().unwrap_or("React.createElement"),
+        "pragmaFrag": jsx.pragma_frag.as_deref().unwrap_or("React.Fragment"),
+        "development": jsx.development.unwrap_or(development),
+    }))
+    .map_err(|e| BundlerError::ConfigError(format!("Invalid JSX options: {}", e)))
+}
+
+/// The pass compiling JSX with `options`
+pub(crate) fn jsx(context: &TransformContext, options: react::Options) -> impl Pass + use<> {
+    react::react(
+        context.cm.clone(),
+        Some(context.comments.clone()),
+        options,
+        context.top_level_mark,
+        context.unresolved_mark,
+    )
+}
+
+/// Lower `source`, plain JavaScript, to syntax the browsers `query` selects
+/// support. With a `core_js` version, polyfills for the features it uses are
+/// imported from core-js.
+pub(crate) fn lower(
+    source: &str,
+    path: &Path,
+    query: &str,
+    core_js: Option<&str>,
+) -> Result<String> {
+    // Built from the `env` shape of `.swcrc`
+    let mut config = serde_json::json!({ "targets": query });
+    if let Some(version) = core_js {
+        config["mode"] = "usage".into();
+        config["coreJs"] = version.into();
+    }
+    let config: preset_env::Config = serde_json::from_value(config)
+        .map_err(|e| BundlerError::ConfigError(format!("Invalid target '{}': {}", query, e)))?;
+    // Turning the config into an `EnvConfig` panics on queries browserslist
+    // rejects, so the query is run through browserslist first
+    lightningcss::targets::Browsers::from_browserslist([query])
+        .map_err(|e| BundlerError::ConfigError(format!("Invalid target '{}': {}", query, e)))?;
+    let config = preset_env::EnvConfig::from(config);
+
+    transform(source, path, Syntax::Es(EsSyntax::default()), |cx| {
+        preset_env::transform_from_env(
+            cx.unresolved_mark,
+            Some(cx.comments.clone()),
+            config,
+            Assumptions::default(),
+        )
+    })
+}
+
+fn parse_error(cm: &SourceMap, path: &Path, error: ParseError) -> BundlerError {
+    let location = cm.lookup_char_pos(error.span().lo);
+    BundlerError::ParseError {
+        file: path.display().to_string(),
+        message: format!(
+            "{} at {}:{}",
+            error.kind().msg(),
+            location.line,
+            location.col_display + 1
+        ),
+    }
+}
@@ -0,0 +1,305 @@
+//! `tsconfig.json` lookup
+//!
+//! Only `compilerOptions` that change how a file is transpiled are read.
+//! `extends` chains are followed, with the extending file's options winning.
+
+use crate::{
+    BundlerError, Result,
+    config::{DecoratorConfig, DecoratorVersion, JsxConfig, JsxRuntime},
+};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+const FILE_NAME: &str = "tsconfig.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompilerOptions {
+    /// Keep imports and exports exactly as written instead of eliding the
+    /// ones only used as types
+    pub verbatim_module_syntax: Option<bool>,
+    /// Emit class fields as native fields (`[[Define]]` semantics)
+    pub use_define_for_class_fields: Option<bool>,
+    /// Language version, e.g. `"ES2020"` or `"ESNext"`
+    pub target: Option<String>,
+    /// Compile decorators as TypeScript's legacy ones rather than standard
+    pub experimental_decorators: Option<bool>,
+    pub emit_decorator_metadata: Option<bool>,
+    /// `"react"`, `"react-jsx"`, `"react-jsxdev"`, `"preserve"`, ...
+    pub jsx: Option<String>,
+    pub jsx_factory: Option<String>,
+    pub jsx_fragment_factory: Option<String>,
+    pub jsx_import_source: Option<String>,
+}
+
+impl CompilerOptions {
+    /// Options of the `tsconfig.json` governing files in `dir`, or the
+    /// defaults if there is none
+    pub fn find(dir: &Path) -> Result<Self> {
+        match dir
+            .ancestors()
+            .map(|ancestor| ancestor.join(FILE_NAME))
+            .find(|path| path.is_file())
+        {
+            Some(path) => Self::load(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Options of the tsconfig file at `path`, including the ones it extends
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut visited = Vec::new();
+        Self::load_extending(path, &mut visited)
+    }
+
+    fn load_extending(path: &Path, visited: &mut Vec<PathBuf>) -> Result<Self> {
+        if visited.iter().any(|seen| seen == path) {
+            return Err(invalid(path, "circular `extends`"));
+        }
+        visited.push(path.to_path_buf());
+
+        let content = std::fs::read_to_string(path)?;
+        let file: TsConfigFile = serde_json::from_str(&strip_jsonc(&content))
+            .map_err(|e| invalid(path, &e.to_string()))?;
+
+        let options = file.compiler_options.unwrap_or_default();
+        match file.extends {
+            Some(extends) => {
+                let dir = path.parent().unwrap_or(Path::new("."));
+                let base_path = find_extended(&extends, dir)
+                    .ok_or_else(|| invalid(path, &format!("cannot find '{}'", extends)))?;
+                Ok(options.or(Self::load_extending(&base_path, visited)?))
+            }
+            None => Ok(options),
+        }
+    }
+
+    /// Fill options unset in `self` from `base`
+    fn or(self, base: Self) -> Self {
+        Self {
+            verbatim_module_syntax: self.verbatim_module_syntax.or(base.verbatim_module_syntax),
+            use_define_for_class_fields: self
+                .use_define_for_class_fields
+                .or(base.use_define_for_class_fields),
+            target: self.target.or(base.target),
+            experimental_decorators: self
+                .experimental_decorators
+                .or(base.experimental_decorators),
+            emit_decorator_metadata: self
+                .emit_decorator_metadata
+                .or(base.emit_decorator_metadata),
+            jsx: self.jsx.or(base.jsx),
+            jsx_factory: self.jsx_factory.or(base.jsx_factory),
+            jsx_fragment_factory: self.jsx_fragment_factory.or(base.jsx_fragment_factory),
+            jsx_import_source: self.jsx_import_source.or(base.jsx_import_source),
+        }
+    }
+
+    /// The decorator settings these options describe. Like TypeScript 5,
+    /// decorators are standard ones unless `experimentalDecorators` is set.
+    pub fn decorator_config(&self) -> DecoratorConfig {
+        let version = if self.experimental_decorators.unwrap_or(false) {
+            DecoratorVersion::Legacy
+        } else {
+            DecoratorVersion::Standard
+        };
+
+        DecoratorConfig {
+            version: Some(version),
+            emit_metadata: self.emit_decorator_metadata,
+        }
+    }
+
+    /// The JSX settings these options describe. `preserve` has no
+    /// equivalent, since JSX has to be compiled to run in the bundle.
+    pub fn jsx_config(&self) -> JsxConfig {
+        let (runtime, development) = match self.jsx.as_deref() {
+            Some("react") => (Some(JsxRuntime::Classic), None),
+            Some("react-jsx") => (Some(JsxRuntime::Automatic), None),
+            Some("react-jsxdev") => (Some(JsxRuntime::Automatic), Some(true)),
+            _ => (None, None),
+        };
+
+        JsxConfig {
+            runtime,
+            import_source: self.jsx_import_source.clone(),
+            pragma: self.jsx_factory.clone(),
+            pragma_frag: self.jsx_fragment_factory.clone(),
+            development,
+        }
+    }
+
+    /// `useDefineForClassFields`, which TypeScript enables by default from
+    /// ES2022 on
+    pub fn define_class_fields(&self) -> bool {
+        self.use_define_for_class_fields.unwrap_or_else(|| {
+            let target = self.target.as_deref().unwrap_or("ES5").to_ascii_uppercase();
+            target == "ESNEXT"
+                || target
+                    .strip_prefix("ES")
+                    .and_then(|year| year.parse::<u32>().ok())
+                    .is_some_and(|year| year >= 2022)
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TsConfigFile {
+    extends: Option<String>,
+    compiler_options: Option<CompilerOptions>,
+}
+
+/// Locate the file named by `extends`: a relative path, or a path inside an
+/// installed package such as `@tsconfig/node20/tsconfig.json`
+fn find_extended(extends: &str, dir: &Path) -> Option<PathBuf> {
+    let with_extension = |path: PathBuf| {
+        if path.is_file() {
+            Some(path)
+        } else {
+            let path = path.with_extension("json");
+            path.is_file().then_some(path)
+        }
+    };
+
+    if extends.starts_with("./") || extends.starts_with("../") || Path::new(extends).is_absolute() {
+        return with_extension(dir.join(extends));
+    }
+
+    dir.ancestors().find_map(|ancestor| {
+        let package = ancestor.join("node_modules").join(extends);
+        if package.is_dir() {
+            with_extension(package.join(FILE_NAME))
+        } else {
+            with_extension(package)
+        }
+    })
+}
+
+fn invalid(path: &Path, message: &str) -> BundlerError {
+    BundlerError::ConfigError(format!("Invalid {}: {}", path.display(), message))
+}
+
+/// Turn JSON with comments and trailing commas, as tsconfig files allow, into
+/// plain JSON. Lines stay where they were, so errors point at the source.
+pub(crate) fn strip_jsonc(source: &str) -> String {
+    let mut without_comments = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            without_comments.push(c);
+            match c {
+                '\\' => without_comments.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                without_comments.push(c);
+            }
+            ('/', Some('/')) => while chars.next_if(|&c| c != '\n').is_some() {},
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    if c == '\n' {
+                        without_comments.push(c);
+                    }
+                    previous = c;
+                }
+            }
+            _ => without_comments.push(c),
+        }
+    }
+
+    // Drop commas followed only by whitespace and a closing bracket
+    let mut json = String::with_capacity(without_comments.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in without_comments.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = without_comments[index + 1..]
+                .chars()
+                .find(|c| !c.is_whitespace());
+            if matches!(next, Some('}' | ']')) {
+                continue;
+            }
+        }
+        json.push(c);
+    }
+
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_follows_extends() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("node_modules/@tsconfig/base")).unwrap();
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+
+        fs::write(
+            root.join("node_modules/@tsconfig/base/tsconfig.json"),
+            r#"{ "compilerOptions": { "target": "ES2022", "verbatimModuleSyntax": true, "jsx": "react-jsxdev" } }"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("tsconfig.json"),
+            r#"{
+                // Shared settings
+                "extends": "@tsconfig/base",
+                "compilerOptions": {
+                    /* the project's own */
+                    "verbatimModuleSyntax": false,
+                    "jsxImportSource": "preact",
+                    "paths": { "@/*": ["./src/*"], },
+                },
+            }"#,
+        )
+        .unwrap();
+
+        let options = CompilerOptions::find(&root.join("src/nested")).unwrap();
+        assert_eq!(options.verbatim_module_syntax, Some(false));
+        assert_eq!(options.target.as_deref(), Some("ES2022"));
+        assert!(options.define_class_fields());
+        assert_eq!(
+            options.jsx_config(),
+            JsxConfig {
+                runtime: Some(JsxRuntime::Automatic),
+                import_source: Some("preact".to_string()),
+                development: Some(true),
+                ..JsxConfig::default()
+            }
+        );
+
+        let without_tsconfig = TempDir::new().unwrap();
+        assert_eq!(
+            CompilerOptions::find(without_tsconfig.path()).unwrap(),
+            CompilerOptions::default()
+        );
+    }
+}
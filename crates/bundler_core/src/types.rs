@@ -1,6 +1,5 @@
 //! TypeScript type definitions generator
 
-use crate::config::*;
 use std::fs;
 use std::path::Path;
 
@@ -10,18 +9,26 @@ pub fn generate_types() -> Result<(), Box<dyn std::error::Error>> {
 
 declare module 'bundler_core' {
   export interface Config {
+    context?: string;
     entry: string;
     output: OutputConfig;
     module: ModuleConfig;
     resolve: ResolveConfig;
     plugins: string[];
     mode: Mode;
+    platform?: Platform;
+    target?: string;
+    coreJs?: string;
+    css?: CssConfig;
+    wasm?: WasmConfig;
+    envPrefix?: string;
   }
 
   export interface OutputConfig {
     path: string;
     filename: string;
     chunkFilename: string;
+    publicPath?: string;
   }
 
   export interface ModuleConfig {
@@ -29,18 +36,61 @@ declare module 'bundler_core' {
   }
 
   export interface Rule {
-    test: string;
-    useLoader: string;
+    test?: string;
+    use?: RuleUse | RuleUse[];
+    /** @deprecated Use `use` */
+    useLoader?: RuleUse | RuleUse[];
+    include?: string;
     exclude?: string;
+    resourceQuery?: string;
+    oneOf?: Rule[];
+    type?: AssetType;
+    maxInlineSize?: number;
+  }
+
+  export type AssetType = 'asset' | 'asset/resource' | 'asset/inline' | 'asset/source';
+
+  export type RuleUse =
+    | string
+    | { loader: string; options?: { jsx?: JsxConfig; decorators?: DecoratorConfig; [key: string]: unknown } };
+
+  export interface JsxConfig {
+    runtime?: 'Classic' | 'Automatic';
+    importSource?: string;
+    pragma?: string;
+    pragmaFrag?: string;
+    development?: boolean;
+  }
+
+  export interface DecoratorConfig {
+    version?: 'Legacy' | 'Standard';
+    emitMetadata?: boolean;
+  }
+
+  export interface CssConfig {
+    modules?: CssModulesConfig;
+  }
+
+  export interface CssModulesConfig {
+    localIdentName?: string;
+  }
+
+  export interface WasmConfig {
+    exports?: 'Namespace' | 'Init';
+    imports?: Record<string, string>;
   }
 
   export interface ResolveConfig {
     extensions: string[];
     alias: Record<string, string>;
+    symlinks?: boolean;
+    fallback?: Record<string, string | false>;
   }
 
   export type Mode = 'Development' | 'Production';
 
+  export type Platform = 'Web' | 'Node';
+
   export function defineConfig(config: Config): Config;
 }
 "#;
@@ -0,0 +1,119 @@
+use crate::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Produces the source of a virtual module when the compiler needs it
+pub type VirtualModuleFactory = Arc<dyn Fn() -> Result<String> + Send + Sync>;
+
+/// Modules with build-time content, keyed by the specifier they are imported
+/// with (`virtual:routes`, `\0build-info`, ...)
+#[derive(Default)]
+pub struct VirtualModules {
+    modules: HashMap<String, VirtualModuleFactory>,
+}
+
+impl VirtualModules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a virtual module with fixed content
+    pub fn add(&mut self, id: String, content: String) {
+        self.modules
+            .insert(id, Arc::new(move || Ok(content.clone())));
+    }
+
+    /// Register a virtual module whose content is generated on every build
+    pub fn add_factory(&mut self, id: String, factory: VirtualModuleFactory) {
+        self.modules.insert(id, factory);
+    }
+
+    /// Content of `id`, or `None` if it isn't registered here
+    pub fn load(&self, id: &str) -> Option<Result<String>> {
+        self.modules.get(id).map(|factory| factory())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Config,
+        compiler::Compiler,
+        config::{Rule, UseEntry},
+        loader::{Loader, LoaderContext, LoaderOutput},
+    };
+    use async_trait::async_trait;
+
+    /// Appends a comment naming the query the module was requested with
+    struct QueryLoader;
+
+    #[async_trait]
+    impl Loader for QueryLoader {
+        async fn load(
+            &self,
+            input: LoaderOutput,
+            context: &mut LoaderContext,
+        ) -> crate::Result<LoaderOutput> {
+            let query = context.resource_query.clone().unwrap_or_default();
+            Ok(LoaderOutput::new(format!(
+                "{}// {}\n",
+                input.text()?,
+                query
+            )))
+        }
+
+        fn name(&self) -> &str {
+            "query"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_virtual_module_in_bundle() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("src")).unwrap();
+        std::fs::write(
+            root.join("src/index.js"),
+            "import { routes } from 'virtual:routes?locale=en';\nconsole.log(routes);\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("src/home.js"), "export const home = '/';\n").unwrap();
+
+        let mut config = Config {
+            context: Some(root.display().to_string()),
+            entry: root.join("src/index.js").display().to_string(),
+            ..Config::default()
+        };
+        config.module.rules.push(Rule {
+            test: "^virtual:".to_string(),
+            use_loader: vec![UseEntry::new("query")],
+            include: None,
+            exclude: None,
+            resource_query: Some("locale".to_string()),
+            one_of: vec![],
+            asset_type: None,
+            max_inline_size: None,
+        });
+        let mut compiler = Compiler::new(config);
+        compiler.register_loader("query", Box::new(QueryLoader));
+        // Relative to the project root, not to the importer
+        compiler.add_virtual_module(
+            "virtual:routes",
+            "import { home } from './src/home.js';\nexport const routes = [home];\n",
+        );
+        let result = compiler.compile().await.unwrap();
+
+        let main = &result.chunks[0];
+        assert_eq!(
+            main.modules,
+            [
+                root.join("src/home.js").display().to_string(),
+                "virtual:routes?locale=en".to_string(),
+                format!("entry:{}", root.join("src/index.js").display()),
+            ]
+        );
+        let bundle = String::from_utf8_lossy(&result.assets["main.js"]);
+        assert!(bundle.contains("const routes = exports.routes = [home];\n// ?locale=en\n"));
+        assert!(bundle.contains("__webpack_require__(\"virtual:routes?locale=en\")"));
+    }
+}
@@ -0,0 +1,360 @@
+//! WebAssembly modules
+//!
+//! A `.wasm` file is emitted as an asset and stands in the graph as a script
+//! that instantiates it. The modules its import section names are requests
+//! like any other, so a binary built with wasm-bindgen pulls in its JS glue,
+//! and their exports are what the instance imports. Namespaces that aren't
+//! requests, like `env` or `wasi_snapshot_preview1`, come from the module
+//! `wasm.imports` maps them to, or from the caller of an `Init` module.
+
+use crate::{
+    BundlerError, Result,
+    config::{Platform, WasmExports},
+};
+use std::collections::HashMap;
+use std::path::Path;
+
+const MAGIC: &[u8] = b"\0asm";
+const IMPORT_SECTION: u8 = 2;
+
+/// Script of the WebAssembly module for the binary `content` at `path`. The
+/// binary is emitted into `emitted_files` and fetched from under
+/// `public_path` (read next to the bundle on Node.js). `request` gives the
+/// request providing an import namespace, or `None` when it is left to
+/// whoever instantiates the module.
+pub(crate) fn module_source(
+    path: &Path,
+    content: Vec<u8>,
+    exports: WasmExports,
+    public_path: &str,
+    request: impl Fn(&str) -> Option<String>,
+    emitted_files: &mut HashMap<String, Vec<u8>>,
+) -> Result<String> {
+    let imports = import_modules(&content).map_err(|message| BundlerError::ParseError {
+        file: path.display().to_string(),
+        message,
+    })?;
+
+    let filename = crate::asset::filename(path, &content);
+    let url = serde_json::to_string(&format!("{}{}", public_path, filename)).unwrap_or_default();
+    emitted_files.insert(filename, content);
+
+    let mut provided = Vec::new();
+    for name in imports {
+        match request(&name) {
+            Some(request) => provided.push(format!(
+                "  {}: require({})",
+                serde_json::to_string(&name).unwrap_or_default(),
+                serde_json::to_string(&request).unwrap_or_default()
+            )),
+            // Only an `Init` module has a caller to pass the namespace
+            None if exports == WasmExports::Init => {}
+            None => {
+                return Err(BundlerError::ParseError {
+                    file: path.display().to_string(),
+                    message: format!(
+                        "imports from '{}', which isn't a module; map it to one in wasm.imports, or set wasm.exports to Init to pass it when instantiating",
+                        name
+                    ),
+                });
+            }
+        }
+    }
+    let imports = provided.join(",\n");
+    let imports = if imports.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{\n{}\n}}", imports)
+    };
+
+    Ok(match exports {
+        WasmExports::Namespace => format!(
+            "var instantiated = __webpack_require__.wasm({}, {});\n__webpack_require__.w.push(module.w = instantiated.then(function(wasmExports) {{\n  Object.assign(module.exports, wasmExports);\n}}));",
+            url, imports
+        ),
+        WasmExports::Init => format!(
+            "module.exports = function(imports) {{\n  return __webpack_require__.wasm({}, Object.assign({}, imports));\n}};",
+            url, imports
+        ),
+    })
+}
+
+/// Runtime helpers of bundles with WebAssembly modules.
+/// `__webpack_require__.wasm` instantiates the binary at a URL and resolves
+/// to its exports; `__webpack_require__.w` holds the instantiations the
+/// entry waits for. Each module's own is its `w` as well, which loading an
+/// async chunk waits for.
+pub(crate) fn runtime(platform: Platform) -> &'static str {
+    match platform {
+        Platform::Web => {
+            r#"  __webpack_require__.wasm = function(url, imports) {
+    var response = fetch(url);
+    var instantiated = typeof WebAssembly.instantiateStreaming === "function"
+      ? WebAssembly.instantiateStreaming(response, imports)
+      : response.then(function(response) { return response.arrayBuffer(); })
+          .then(function(bytes) { return WebAssembly.instantiate(bytes, imports); });
+    return instantiated.then(function(result) { return result.instance.exports; });
+  };
+  __webpack_require__.w = [];
+"#
+        }
+        Platform::Node => {
+            r#"  __webpack_require__.wasm = function(url, imports) {
+    var bytes = require("fs").readFileSync(require("path").join(__dirname, url));
+    return WebAssembly.instantiate(bytes, imports)
+      .then(function(result) { return result.instance.exports; });
+  };
+  __webpack_require__.w = [];
+"#
+        }
+    }
+}
+
+/// Names of the modules the binary `wasm` imports from, in the order its
+/// import section first lists them
+fn import_modules(wasm: &[u8]) -> std::result::Result<Vec<String>, String> {
+    let mut reader = Reader { bytes: wasm, at: 0 };
+    if reader.take(4)? != MAGIC {
+        return Err("not a WebAssembly binary".to_string());
+    }
+    let version = reader.take(4)?;
+    if version != [1, 0, 0, 0] {
+        return Err(format!("unsupported WebAssembly version {:?}", version));
+    }
+
+    let mut modules = Vec::new();
+    while reader.at < wasm.len() {
+        let id = reader.byte()?;
+        let size = reader.leb128()? as usize;
+        let section = reader.take(size)?;
+        if id != IMPORT_SECTION {
+            continue;
+        }
+
+        let mut reader = Reader {
+            bytes: section,
+            at: 0,
+        };
+        for _ in 0..reader.leb128()? {
+            let module = reader.name()?;
+            reader.name()?;
+            reader.import_description()?;
+            if !modules.contains(&module) {
+                modules.push(module);
+            }
+        }
+    }
+
+    Ok(modules)
+}
+
+/// Cursor over the bytes of a binary, or of one of its sections
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> std::result::Result<&'a [u8], String> {
+        let bytes = self
+            .bytes
+            .get(self.at..self.at.saturating_add(len))
+            .ok_or_else(|| format!("unexpected end of binary at byte {}", self.at))?;
+        self.at += len;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> std::result::Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// An unsigned LEB128 integer
+    fn leb128(&mut self) -> std::result::Result<u64, String> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(format!("integer too long at byte {}", self.at))
+    }
+
+    fn name(&mut self) -> std::result::Result<String, String> {
+        let len = self.leb128()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| format!("name is not UTF-8 at byte {}", self.at))
+    }
+
+    /// Skip what an import is: a function, table, memory, global or tag
+    fn import_description(&mut self) -> std::result::Result<(), String> {
+        match self.byte()? {
+            // Function or tag: a type index, after an attribute for tags
+            0x00 => {
+                self.leb128()?;
+            }
+            0x04 => {
+                self.byte()?;
+                self.leb128()?;
+            }
+            // Table: reference type, then limits
+            0x01 => {
+                self.byte()?;
+                self.limits()?;
+            }
+            0x02 => self.limits()?,
+            // Global: value type and mutability
+            0x03 => {
+                self.take(2)?;
+            }
+            kind => {
+                return Err(format!(
+                    "unknown import kind {:#04x} at byte {}",
+                    kind, self.at
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn limits(&mut self) -> std::result::Result<(), String> {
+        let flags = self.byte()?;
+        self.leb128()?;
+        if flags & 1 != 0 {
+            self.leb128()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A binary importing `log` from `./env.js`, `memory` from `env` and
+    /// `now` from `./env.js` again
+    fn binary() -> Vec<u8> {
+        let mut imports = vec![3];
+        for (module, field, description) in [
+            ("./env.js", "log", &[0x00, 0x00][..]),
+            ("env", "memory", &[0x02, 0x01, 0x01, 0x80, 0x01]),
+            ("./env.js", "now", &[0x00, 0x01]),
+        ] {
+            imports.push(module.len() as u8);
+            imports.extend(module.as_bytes());
+            imports.push(field.len() as u8);
+            imports.extend(field.as_bytes());
+            imports.extend(description);
+        }
+
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        // An empty custom section, which is skipped
+        wasm.extend([0, 1, 0]);
+        wasm.push(IMPORT_SECTION);
+        wasm.push(imports.len() as u8);
+        wasm.extend(imports);
+        wasm
+    }
+
+    #[test]
+    fn test_import_modules() {
+        assert_eq!(import_modules(&binary()).unwrap(), ["./env.js", "env"]);
+        assert_eq!(
+            import_modules(b"\0asm\x01\0\0\0").unwrap(),
+            Vec::<String>::new()
+        );
+        assert!(import_modules(b"(module)").is_err());
+        assert!(import_modules(&binary()[..20]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bundle_wasm_module() {
+        use crate::{Config, compiler::Compiler};
+        use std::fs;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(
+            root.join("index.js"),
+            "import { decode } from './codec.wasm';\ndecode();\n",
+        )
+        .unwrap();
+        fs::write(root.join("env.js"), "export function log() {}\n").unwrap();
+        fs::create_dir(root.join("env")).unwrap();
+        fs::write(root.join("env/index.js"), "export const memory = null;\n").unwrap();
+        let wasm = binary();
+        fs::write(root.join("codec.wasm"), &wasm).unwrap();
+        fs::write(
+            root.join("lazy.js"),
+            "import('./decode.js').then(function(codec) { codec.decode(); });\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("decode.js"),
+            "import { decode } from './codec.wasm';\nexport { decode };\n",
+        )
+        .unwrap();
+
+        let compile = |entry: &'static str, exports, env: Option<&'static str>| async move {
+            let mut config = Config {
+                entry: root.join(entry).display().to_string(),
+                context: Some(root.display().to_string()),
+                ..Config::default()
+            };
+            config.output.public_path = "/static/".to_string();
+            config.wasm.exports = exports;
+            if let Some(env) = env {
+                config
+                    .wasm
+                    .imports
+                    .insert("env".to_string(), env.to_string());
+            }
+            Compiler::new(config).compile().await
+        };
+
+        let result = compile("index.js", WasmExports::Namespace, Some("./env/index.js"))
+            .await
+            .unwrap();
+        let filename = crate::asset::filename(Path::new("codec.wasm"), &wasm);
+        assert_eq!(result.assets[&filename], wasm);
+        let bundle = String::from_utf8_lossy(&result.assets["main.js"]);
+        assert!(bundle.contains(&format!(
+            "__webpack_require__.wasm(\"/static/{}\", {{\n  \"./env.js\": __webpack_require__({:?}),",
+            filename,
+            root.join("env.js").canonicalize().unwrap().display().to_string()
+        )));
+        assert!(bundle.contains(&format!(
+            "  \"env\": __webpack_require__({:?})\n}}",
+            root.join("env/index.js").canonicalize().unwrap().display().to_string()
+        )));
+        assert!(bundle.contains("return Promise.all(__webpack_require__.w).then(function() {"));
+
+        // Nothing provides `env` to a namespace, but an `Init` caller can
+        let err = compile("index.js", WasmExports::Namespace, None)
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("imports from 'env'"));
+        let result = compile("index.js", WasmExports::Init, None).await.unwrap();
+        let bundle = String::from_utf8_lossy(&result.assets["main.js"]);
+        assert!(bundle.contains("module.exports = function(imports) {"));
+        assert!(!bundle.contains("\"env\":"));
+        assert!(!bundle.contains("Promise.all"));
+
+        // Loading an async chunk waits for its WebAssembly too
+        let result = compile("lazy.js", WasmExports::Namespace, Some("./env/index.js"))
+            .await
+            .unwrap();
+        let bundle = String::from_utf8_lossy(&result.assets["main.js"]);
+        assert!(bundle.contains(&format!(
+            "var chunkWasm = {{\"decode.chunk.js\": [{:?}]}};",
+            root.join("codec.wasm").canonicalize().unwrap().display().to_string()
+        )));
+        assert!(bundle.contains("return installedModules[moduleId].w;"));
+        assert!(!bundle.contains("return Promise.all(__webpack_require__.w)"));
+        let chunk = String::from_utf8_lossy(&result.assets["decode.chunk.js"]);
+        assert!(chunk.contains("__webpack_require__.w.push(module.w = instantiated.then("));
+    }
+}
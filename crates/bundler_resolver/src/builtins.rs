@@ -0,0 +1,78 @@
+//! Node.js built-in modules
+
+/// Modules importable with or without the `node:` prefix, as listed by
+/// `require('module').builtinModules`
+const BUILTIN_MODULES: &[&str] = &[
+    "assert",
+    "assert/strict",
+    "async_hooks",
+    "buffer",
+    "child_process",
+    "cluster",
+    "console",
+    "constants",
+    "crypto",
+    "dgram",
+    "diagnostics_channel",
+    "dns",
+    "dns/promises",
+    "domain",
+    "events",
+    "fs",
+    "fs/promises",
+    "http",
+    "http2",
+    "https",
+    "inspector",
+    "inspector/promises",
+    "module",
+    "net",
+    "os",
+    "path",
+    "path/posix",
+    "path/win32",
+    "perf_hooks",
+    "process",
+    "punycode",
+    "querystring",
+    "readline",
+    "readline/promises",
+    "repl",
+    "stream",
+    "stream/consumers",
+    "stream/promises",
+    "stream/web",
+    "string_decoder",
+    "sys",
+    "timers",
+    "timers/promises",
+    "tls",
+    "trace_events",
+    "tty",
+    "url",
+    "util",
+    "util/types",
+    "v8",
+    "vm",
+    "wasi",
+    "worker_threads",
+    "zlib",
+];
+
+/// Modules that only exist with the `node:` prefix
+const PREFIX_ONLY_MODULES: &[&str] = &["sea", "sqlite", "test", "test/reporters"];
+
+/// The built-in `request` names, without any `node:` prefix, if it is one
+pub(crate) fn builtin_name(request: &str) -> Option<&str> {
+    match request.strip_prefix("node:") {
+        Some(name) => {
+            (BUILTIN_MODULES.contains(&name) || PREFIX_ONLY_MODULES.contains(&name)).then_some(name)
+        }
+        None => BUILTIN_MODULES.contains(&request).then_some(request),
+    }
+}
+
+/// Whether `request` names a Node.js built-in module, e.g. `fs` or `node:fs`
+pub fn is_builtin(request: &str) -> bool {
+    builtin_name(request).is_some()
+}
@@ -0,0 +1,70 @@
+//! The `exports` field of `package.json`
+//!
+//! See <https://nodejs.org/api/packages.html#package-entry-points>. Every
+//! target a request could map to is returned, in the order the conditions
+//! pick them, so the resolver can fall through to the next one when a file
+//! is missing and list them all when none exists.
+
+use serde_json::{Map, Value};
+
+/// Paths inside the package, relative to it, that `exports` maps `subpath`
+/// (`.` for the package itself, `./feature` for `pkg/feature`) to under
+/// `conditions`. `default` always matches.
+pub(crate) fn targets(exports: &Value, subpath: &str, conditions: &[String]) -> Vec<String> {
+    let mut found = Vec::new();
+
+    match exports {
+        Value::Object(map) if map.keys().any(|key| key.starts_with('.')) => {
+            if let Some(target) = map.get(subpath) {
+                collect(target, None, conditions, &mut found);
+            } else if let Some((target, matched)) = match_pattern(map, subpath) {
+                collect(target, Some(matched), conditions, &mut found);
+            }
+        }
+        // Without subpath keys, `exports` only describes the package itself
+        _ if subpath == "." => collect(exports, None, conditions, &mut found),
+        _ => {}
+    }
+
+    found
+}
+
+/// The target of the `./lib/*` style key matching `subpath`, with the part
+/// `*` stands for. The key with the longest prefix wins.
+fn match_pattern<'a>(
+    map: &'a Map<String, Value>,
+    subpath: &'a str,
+) -> Option<(&'a Value, &'a str)> {
+    map.iter()
+        .filter_map(|(key, target)| {
+            let (prefix, suffix) = key.split_once('*')?;
+            let matched = subpath.strip_prefix(prefix)?.strip_suffix(suffix)?;
+            Some((prefix.len(), target, matched))
+        })
+        .max_by_key(|(prefix_len, ..)| *prefix_len)
+        .map(|(_, target, matched)| (target, matched))
+}
+
+fn collect(target: &Value, matched: Option<&str>, conditions: &[String], found: &mut Vec<String>) {
+    match target {
+        // Targets have to stay inside the package
+        Value::String(path) if path.starts_with("./") => found.push(match matched {
+            Some(matched) => path.replace('*', matched),
+            None => path.clone(),
+        }),
+        Value::Array(alternatives) => {
+            for alternative in alternatives {
+                collect(alternative, matched, conditions, found);
+            }
+        }
+        Value::Object(branches) => {
+            for (condition, branch) in branches {
+                if condition == "default" || conditions.contains(condition) {
+                    collect(branch, matched, conditions, found);
+                }
+            }
+        }
+        // `null` excludes the subpath
+        _ => {}
+    }
+}
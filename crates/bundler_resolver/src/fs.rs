@@ -0,0 +1,179 @@
+//! File system access used during resolution
+//!
+//! Besides plain files this understands the two kinds of paths Yarn
+//! Plug'n'Play hands out: files inside zip archives in the Yarn cache
+//! (`.yarn/cache/pkg-npm-1.0.0-abc.zip/node_modules/pkg/index.js`) and
+//! `__virtual__` paths that give packages with peer dependencies a distinct
+//! location per set of peers. Callers that need to read a resolved file should
+//! go through [`read`] rather than `std::fs`.
+
+use path_absolutize::Absolutize;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Entry listing of a zip archive, cached per archive path
+struct ZipIndex {
+    files: HashSet<String>,
+    dirs: HashSet<String>,
+}
+
+pub fn is_file(path: &Path) -> bool {
+    let path = devirtualize(path);
+    match split_zip_path(&path) {
+        Some((archive, inner)) => {
+            zip_index(&archive).is_ok_and(|index| index.files.contains(&inner))
+        }
+        None => path.is_file(),
+    }
+}
+
+pub fn is_dir(path: &Path) -> bool {
+    let path = devirtualize(path);
+    match split_zip_path(&path) {
+        Some((archive, inner)) => {
+            inner.is_empty() || zip_index(&archive).is_ok_and(|index| index.dirs.contains(&inner))
+        }
+        None => path.is_dir(),
+    }
+}
+
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    let path = devirtualize(path);
+    match split_zip_path(&path) {
+        Some((archive, inner)) => {
+            let mut archive =
+                zip::ZipArchive::new(std::fs::File::open(archive)?).map_err(io::Error::other)?;
+            let mut entry = archive.by_name(&inner).map_err(|e| match e {
+                zip::result::ZipError::FileNotFound => io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} not found in archive", inner),
+                ),
+                e => io::Error::other(e),
+            })?;
+            let mut content = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut content)?;
+            Ok(content)
+        }
+        None => std::fs::read(path),
+    }
+}
+
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    String::from_utf8(read(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Canonical form of `path`.
+///
+/// Paths inside an archive canonicalize the archive itself and keep the
+/// entry path. `__virtual__` paths are only normalized, since their location
+/// is what identifies the package instance.
+pub fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    let normalized = path.absolutize()?.to_path_buf();
+    if devirtualize(&normalized) != normalized {
+        return Ok(normalized);
+    }
+
+    match split_zip_path(&normalized) {
+        Some((archive, inner)) => Ok(archive.canonicalize()?.join(inner)),
+        None => normalized.canonicalize(),
+    }
+}
+
+/// Whether `path` points inside a zip archive or a `__virtual__` directory,
+/// i.e. whether it has to be read through this module
+pub fn is_special_path(path: &Path) -> bool {
+    let devirtualized = devirtualize(path);
+    devirtualized != path || split_zip_path(&devirtualized).is_some()
+}
+
+/// Map a `__virtual__` path to the location it stands for.
+///
+/// `<base>/__virtual__/<name>-virtual-<hash>/<depth>/<rest>` refers to
+/// `<rest>` relative to `<base>` with `<depth>` directories removed.
+pub(crate) fn devirtualize(path: &Path) -> PathBuf {
+    let components: Vec<Component> = path.components().collect();
+    let Some(index) = components
+        .iter()
+        .position(|component| component.as_os_str() == "__virtual__")
+    else {
+        return path.to_path_buf();
+    };
+
+    let depth = components
+        .get(index + 2)
+        .and_then(|component| component.as_os_str().to_str()?.parse::<usize>().ok());
+    let Some(depth) = depth else {
+        return path.to_path_buf();
+    };
+
+    let mut base: PathBuf = components[..index].iter().collect();
+    for _ in 0..depth {
+        base.pop();
+    }
+    components[index + 3..]
+        .iter()
+        .fold(base, |path, component| path.join(component))
+}
+
+/// Split a path such as `/cache/pkg.zip/node_modules/pkg/a.js` into the
+/// archive path and the entry path inside it
+fn split_zip_path(path: &Path) -> Option<(PathBuf, String)> {
+    let mut archive = PathBuf::new();
+    let mut components = path.components();
+
+    while let Some(component) = components.next() {
+        archive.push(component);
+        let is_archive = component
+            .as_os_str()
+            .to_str()
+            .is_some_and(|name| name.ends_with(".zip"));
+
+        if is_archive && archive.is_file() {
+            let inner = components
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            return Some((archive, inner));
+        }
+    }
+
+    None
+}
+
+fn zip_index(archive: &Path) -> io::Result<Arc<ZipIndex>> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, Arc<ZipIndex>>>> = OnceLock::new();
+
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(index) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(archive) {
+        return Ok(index.clone());
+    }
+
+    let zip = zip::ZipArchive::new(std::fs::File::open(archive)?).map_err(io::Error::other)?;
+    let mut files = HashSet::new();
+    let mut dirs = HashSet::new();
+
+    for entry_name in zip.file_names() {
+        let name = entry_name.trim_end_matches('/');
+        if entry_name.ends_with('/') {
+            dirs.insert(name.to_string());
+        } else {
+            files.insert(name.to_string());
+        }
+
+        // Archives don't always list directories, so derive them from files
+        let mut parent = name;
+        while let Some(end) = parent.rfind('/') {
+            parent = &parent[..end];
+            dirs.insert(parent.to_string());
+        }
+    }
+
+    let index = Arc::new(ZipIndex { files, dirs });
+    cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(archive.to_path_buf(), index.clone());
+    Ok(index)
+}
@@ -3,13 +3,53 @@
 //! This crate handles resolving module imports to actual file paths,
 //! similar to how Node.js and Webpack resolve modules.
 
+mod builtins;
+mod exports;
+pub mod fs;
+mod pnp;
+mod suggestions;
+
+pub use builtins::is_builtin;
+use path_absolutize::Absolutize;
+use pnp::{PnpManifest, PnpResolution};
+use std::collections::HashMap;
+use std::fmt;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum ResolverError {
-    #[error("Module not found: {request} from {context}")]
-    ModuleNotFound { request: String, context: String },
+    #[error(
+        "Module not found: {request} from {context}{}",
+        render_hints(.tried, .suggestions)
+    )]
+    ModuleNotFound {
+        request: String,
+        context: String,
+        /// Every path the resolver looked at, in the order it tried them
+        tried: Vec<Candidate>,
+        /// Existing files with a name close to the request
+        suggestions: Vec<String>,
+    },
+
+    #[error("{issuer} tried to access {request} from {context}, but {reason}")]
+    UndeclaredDependency {
+        request: String,
+        issuer: String,
+        context: String,
+        reason: String,
+    },
+
+    #[error(
+        "Module not found: {request} from {context} is a Node.js built-in, which is not \
+         available in the browser. Add a resolve.fallback entry to use a polyfill, or map it \
+         to false for an empty module"
+    )]
+    NodeBuiltin { request: String, context: String },
+
+    #[error("Invalid Plug'n'Play manifest {path}: {message}")]
+    InvalidPnpManifest { path: String, message: String },
 
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -17,14 +57,133 @@ pub enum ResolverError {
 
 pub type Result<T> = std::result::Result<T, ResolverError>;
 
+/// A path probed while resolving a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub path: PathBuf,
+    pub kind: CandidateKind,
+}
+
+/// Why a candidate path was probed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandidateKind {
+    /// The request joined to the context directory as-is
+    Exact,
+    /// The request with one of the configured extensions appended
+    Extension,
+    /// An `index` file inside the requested directory
+    DirectoryIndex,
+    /// A package directory inside a `node_modules` folder
+    NodeModules,
+    /// A file the `exports` field of a package's `package.json` maps the
+    /// request to under the configured conditions
+    PackageExports,
+    /// The file named by the `main` field of a package's `package.json`
+    PackageMain,
+    /// A package location taken from a Yarn Plug'n'Play manifest
+    PnpPackage,
+}
+
+impl fmt::Display for CandidateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            CandidateKind::Exact => "exact path",
+            CandidateKind::Extension => "with extension",
+            CandidateKind::DirectoryIndex => "directory index",
+            CandidateKind::NodeModules => "node_modules package",
+            CandidateKind::PackageExports => "package.json exports",
+            CandidateKind::PackageMain => "package.json main",
+            CandidateKind::PnpPackage => "Plug'n'Play package",
+        };
+        f.write_str(label)
+    }
+}
+
+fn render_hints(tried: &[Candidate], suggestions: &[String]) -> String {
+    let mut hints = String::new();
+
+    if !tried.is_empty() {
+        hints.push_str("\n  Tried:");
+        for candidate in tried {
+            hints.push_str(&format!(
+                "\n    {} ({})",
+                candidate.path.display(),
+                candidate.kind
+            ));
+        }
+    }
+
+    if !suggestions.is_empty() {
+        hints.push_str("\n  Did you mean:");
+        for suggestion in suggestions {
+            hints.push_str(&format!("\n    {}", suggestion));
+        }
+    }
+
+    hints
+}
+
+/// What a request resolved to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// A file on disk, or inside a Plug'n'Play zip archive
+    File(PathBuf),
+    /// A Node.js built-in left for the runtime to provide, e.g. `node:fs`
+    Builtin(String),
+    /// A built-in replaced by an empty module through a `false` fallback
+    Empty,
+    /// A module whose content is supplied at build time rather than read from
+    /// disk, requested as `virtual:<name>` or `\0<name>`
+    Virtual(String),
+}
+
+impl Resolution {
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Resolution::File(path) => Some(path),
+            _ => None,
+        }
+    }
+}
+
+/// Replacement for a Node.js built-in on platforms that don't provide it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fallback {
+    /// Resolve this request instead, e.g. `path-browserify`
+    Module(String),
+    /// Use an empty module
+    Empty,
+}
+
 pub struct Resolver {
     extensions: Vec<String>,
+    symlinks: bool,
+    /// Real path of every file resolved so far, mapped to the path it was
+    /// first returned as. Only consulted when `symlinks` is off.
+    seen: Mutex<HashMap<PathBuf, PathBuf>>,
+    pnp: bool,
+    /// Plug'n'Play manifests loaded so far, by manifest path
+    pnp_manifests: Mutex<HashMap<PathBuf, Arc<PnpManifest>>>,
+    externalize_builtins: bool,
+    fallback: HashMap<String, Fallback>,
+    /// Aliases sorted longest key first, so the most specific one wins
+    alias: Vec<(String, String)>,
+    /// Conditions matched in `package.json` `exports`, besides `default`
+    conditions: Vec<String>,
 }
 
 impl Resolver {
     pub fn new() -> Self {
         Self {
             extensions: vec![".js".to_string(), ".ts".to_string(), ".json".to_string()],
+            symlinks: true,
+            seen: Mutex::new(HashMap::new()),
+            pnp: true,
+            pnp_manifests: Mutex::new(HashMap::new()),
+            externalize_builtins: false,
+            fallback: HashMap::new(),
+            alias: Vec::new(),
+            conditions: vec!["import".to_string(), "module".to_string()],
         }
     }
 
@@ -33,56 +192,506 @@ impl Resolver {
         self
     }
 
-    pub async fn resolve(&self, request: &str, context: &Path) -> Result<PathBuf> {
-        // Handle relative imports
-        if request.starts_with("./") || request.starts_with("../") {
-            return self.resolve_relative(request, context).await;
-        }
+    /// Whether resolved paths are replaced by their real (symlink-free) path.
+    ///
+    /// Defaults to `true`, like webpack's `resolve.symlinks`. When disabled the
+    /// path is kept as it was reached, except that a file reached again through
+    /// a different link resolves to the path it was first seen under, so it
+    /// still ends up in the module graph once.
+    pub fn with_symlinks(mut self, symlinks: bool) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
 
-        // Handle absolute imports (for now, treat as relative to context)
-        // In a real implementation, you'd check node_modules, etc.
-        self.resolve_relative(request, context).await
+    /// Whether bare specifiers go through a Yarn Plug'n'Play manifest
+    /// (`.pnp.data.json` or `.pnp.cjs`) when one is found above the importer.
+    /// Defaults to `true`.
+    pub fn with_pnp(mut self, pnp: bool) -> Self {
+        self.pnp = pnp;
+        self
     }
 
-    async fn resolve_relative(&self, request: &str, context: &Path) -> Result<PathBuf> {
-        let context_dir = if context.is_file() {
-            context.parent().unwrap_or(context)
-        } else {
-            context
-        };
+    /// Leave Node.js built-ins (`fs`, `node:path`, ...) to the runtime.
+    ///
+    /// Meant for bundles running on Node.js. When disabled, which is the
+    /// default, a built-in that isn't installed as a package resolves through
+    /// the fallback map or fails with [`ResolverError::NodeBuiltin`].
+    pub fn with_externalize_builtins(mut self, externalize_builtins: bool) -> Self {
+        self.externalize_builtins = externalize_builtins;
+        self
+    }
+
+    /// Replacements for Node.js built-ins, keyed by name without the `node:`
+    /// prefix, like webpack's `resolve.fallback`
+    pub fn with_fallback(mut self, fallback: HashMap<String, Fallback>) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Replace the start of requests, like webpack's `resolve.alias`.
+    ///
+    /// A key matches the request itself and anything below it, so `@ui`
+    /// rewrites both `@ui` and `@ui/button`; a key ending in `$` only matches
+    /// exactly. Targets are resolved from the importer like any other request,
+    /// so they should be absolute paths or package names.
+    pub fn with_alias(mut self, alias: HashMap<String, String>) -> Self {
+        let mut alias: Vec<(String, String)> = alias.into_iter().collect();
+        alias.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        self.alias = alias;
+        self
+    }
+
+    /// Conditions picking the entry of a package's `package.json` `exports`,
+    /// like webpack's `resolve.conditionNames`. `default` always matches.
+    /// Defaults to `import` and `module`.
+    pub fn with_conditions(mut self, conditions: Vec<String>) -> Self {
+        self.conditions = conditions;
+        self
+    }
+
+    /// Resolve `request` imported from `context`. A resource query or
+    /// fragment on the request (`./icon.svg?raw`, `./data.json#section`) is
+    /// ignored here; use [`parse_request`] to get hold of it.
+    pub async fn resolve(&self, request: &str, context: &Path) -> Result<Resolution> {
+        self.resolve_sync(request, context)
+    }
+
+    /// Blocking version of [`Resolver::resolve`], for tools that don't run
+    /// an async runtime
+    pub fn resolve_sync(&self, request: &str, context: &Path) -> Result<Resolution> {
+        self.resolve_traced(request, context)
+            .map(|(resolution, _)| resolution)
+    }
 
-        let mut candidate = context_dir.join(request);
+    /// Resolve `request` and also return every candidate that was tried and
+    /// rejected on the way, in order. When resolution fails the same list is
+    /// part of [`ResolverError::ModuleNotFound`].
+    pub fn resolve_traced(
+        &self,
+        request: &str,
+        context: &Path,
+    ) -> Result<(Resolution, Vec<Candidate>)> {
+        let request = self.apply_alias(parse_request(request).path);
+        let request = request.as_str();
+        let mut tried = Vec::new();
 
-        // Try the exact path first
-        if candidate.exists() {
-            return Ok(candidate.canonicalize()?);
+        if is_virtual(request) {
+            return Ok((Resolution::Virtual(request.to_string()), tried));
         }
 
-        // Try with extensions
-        for ext in &self.extensions {
-            let mut candidate_with_ext = candidate.clone();
-            candidate_with_ext.set_extension(&ext[1..]); // Remove the dot
+        let Some(name) = builtins::builtin_name(request) else {
+            let path = self.resolve_file(request, context, &mut tried)?;
+            return Ok((Resolution::File(path), tried));
+        };
+
+        if self.externalize_builtins {
+            return Ok((Resolution::Builtin(request.to_string()), tried));
+        }
+
+        // An installed package wins over the fallback, as in webpack. A
+        // `node:` request can only ever mean the built-in.
+        if request == name {
+            match self.resolve_package(request, context, &mut tried) {
+                Ok(path) => return Ok((Resolution::File(path), tried)),
+                Err(ResolverError::ModuleNotFound {
+                    tried: package_tried,
+                    ..
+                }) => tried = package_tried,
+                Err(e) => return Err(e),
+            }
+        }
 
-            if candidate_with_ext.exists() {
-                return Ok(candidate_with_ext.canonicalize()?);
+        match self.fallback.get(name) {
+            Some(Fallback::Module(fallback)) => {
+                let path = self.resolve_file(fallback, context, &mut tried)?;
+                Ok((Resolution::File(path), tried))
             }
+            Some(Fallback::Empty) => Ok((Resolution::Empty, tried)),
+            None => Err(ResolverError::NodeBuiltin {
+                request: request.to_string(),
+                context: context.display().to_string(),
+            }),
         }
+    }
 
-        // Try as directory with index file
-        if candidate.is_dir() {
-            for ext in &self.extensions {
-                let index_file = candidate.join(format!("index{}", ext));
-                if index_file.exists() {
-                    return Ok(index_file.canonicalize()?);
+    fn apply_alias(&self, request: &str) -> String {
+        for (key, target) in &self.alias {
+            if let Some(exact) = key.strip_suffix('$') {
+                if request == exact {
+                    return target.clone();
                 }
+                continue;
+            }
+
+            if request == key {
+                return target.clone();
+            }
+            if let Some(rest) = request
+                .strip_prefix(key.as_str())
+                .filter(|rest| rest.starts_with('/'))
+            {
+                return format!("{}{}", target.trim_end_matches('/'), rest);
+            }
+        }
+
+        request.to_string()
+    }
+
+    fn resolve_file(
+        &self,
+        request: &str,
+        context: &Path,
+        tried: &mut Vec<Candidate>,
+    ) -> Result<PathBuf> {
+        // Handle relative and absolute imports
+        if request.starts_with("./")
+            || request.starts_with("../")
+            || request == "."
+            || request == ".."
+            || Path::new(request).is_absolute()
+        {
+            return self.resolve_relative(request, context, tried);
+        }
+
+        // Everything else is a package in a node_modules directory
+        self.resolve_package(request, context, tried)
+    }
+
+    fn resolve_relative(
+        &self,
+        request: &str,
+        context: &Path,
+        tried: &mut Vec<Candidate>,
+    ) -> Result<PathBuf> {
+        let context_dir = context_dir(context);
+        let candidate = context_dir.join(request);
+
+        if let Some(path) = self.try_file_or_directory(&candidate, tried)? {
+            return Ok(path);
+        }
+
+        Err(ResolverError::ModuleNotFound {
+            request: request.to_string(),
+            context: context.display().to_string(),
+            tried: std::mem::take(tried),
+            suggestions: suggestions::suggest(request, context_dir),
+        })
+    }
+
+    /// Look for `request` in the `node_modules` directories above `context`.
+    ///
+    /// The lookup always starts from the real location of `context`. Package
+    /// managers with a virtual store (pnpm's `node_modules/.pnpm`) link each
+    /// package next to its own dependencies and peers, so walking up from the
+    /// linked path would miss them.
+    fn resolve_package(
+        &self,
+        request: &str,
+        context: &Path,
+        tried: &mut Vec<Candidate>,
+    ) -> Result<PathBuf> {
+        let (package_name, subpath) = split_package_request(request);
+        let context_dir = context_dir(context);
+        let real_context_dir =
+            fs::canonicalize(context_dir).unwrap_or_else(|_| context_dir.to_path_buf());
+
+        if let Some(manifest) = self.pnp_manifest(&real_context_dir)?
+            && let PnpResolution::Resolved(package_dir) =
+                manifest.resolve_package(package_name, &real_context_dir)?
+        {
+            tried.push(Candidate {
+                path: package_dir.clone(),
+                kind: CandidateKind::PnpPackage,
+            });
+
+            let found = self.try_package(&package_dir, subpath, tried)?;
+
+            return found.ok_or_else(|| ResolverError::ModuleNotFound {
+                request: request.to_string(),
+                context: context.display().to_string(),
+                tried: std::mem::take(tried),
+                suggestions: Vec::new(),
+            });
+        }
+
+        for dir in real_context_dir.ancestors() {
+            // There is never a node_modules/node_modules worth looking at
+            if dir.file_name().is_some_and(|name| name == "node_modules") {
+                continue;
+            }
+
+            let package_dir = dir.join("node_modules").join(package_name);
+            if !fs::is_dir(&package_dir) {
+                tried.push(Candidate {
+                    path: package_dir,
+                    kind: CandidateKind::NodeModules,
+                });
+                continue;
+            }
+
+            let found = self.try_package(&package_dir, subpath, tried)?;
+            if let Some(path) = found {
+                return Ok(path);
             }
         }
 
         Err(ResolverError::ModuleNotFound {
             request: request.to_string(),
             context: context.display().to_string(),
+            tried: std::mem::take(tried),
+            suggestions: Vec::new(),
         })
     }
+
+    /// Resolve `subpath` inside `package_dir`, or the package itself without
+    /// one. The `exports` of its `package.json` come first; when none of the
+    /// files they map to exist, the request is looked up the way packages
+    /// without `exports` are.
+    fn try_package(
+        &self,
+        package_dir: &Path,
+        subpath: Option<&str>,
+        tried: &mut Vec<Candidate>,
+    ) -> Result<Option<PathBuf>> {
+        if let Some(path) = self.try_package_exports(package_dir, subpath, tried)? {
+            return Ok(Some(path));
+        }
+
+        match subpath {
+            Some(subpath) => self.try_file_or_directory(&package_dir.join(subpath), tried),
+            None => self.try_package_main(package_dir, tried),
+        }
+    }
+
+    /// Try each file the `package.json` `exports` of `package_dir` maps
+    /// `subpath` to under the configured conditions
+    fn try_package_exports(
+        &self,
+        package_dir: &Path,
+        subpath: Option<&str>,
+        tried: &mut Vec<Candidate>,
+    ) -> Result<Option<PathBuf>> {
+        let Some(exports) = fs::read_to_string(&package_dir.join("package.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|mut json| json.get_mut("exports").map(serde_json::Value::take))
+        else {
+            return Ok(None);
+        };
+
+        let subpath = subpath.map_or_else(|| ".".to_string(), |subpath| format!("./{}", subpath));
+        for target in exports::targets(&exports, &subpath, &self.conditions) {
+            let candidate = package_dir.join(target.strip_prefix("./").unwrap_or(&target));
+            if let Some(path) = self.probe(candidate, CandidateKind::PackageExports, tried)? {
+                return Ok(Some(path));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Resolve a package directory through its `package.json` `main` field,
+    /// falling back to its index file
+    fn try_package_main(
+        &self,
+        package_dir: &Path,
+        tried: &mut Vec<Candidate>,
+    ) -> Result<Option<PathBuf>> {
+        let manifest = package_dir.join("package.json");
+        if let Ok(content) = fs::read_to_string(&manifest) {
+            let main = serde_json::from_str::<serde_json::Value>(&content)
+                .ok()
+                .and_then(|json| json.get("main")?.as_str().map(str::to_string));
+
+            if let Some(main) = main {
+                let candidate = package_dir.join(&main);
+                if let Some(path) =
+                    self.probe(candidate.clone(), CandidateKind::PackageMain, tried)?
+                {
+                    return Ok(Some(path));
+                }
+                if let Some(path) = self.try_file_or_directory(&candidate, tried)? {
+                    return Ok(Some(path));
+                }
+            }
+        }
+
+        self.try_directory(package_dir, tried)
+    }
+
+    fn try_file_or_directory(
+        &self,
+        candidate: &Path,
+        tried: &mut Vec<Candidate>,
+    ) -> Result<Option<PathBuf>> {
+        if let Some(path) = self.try_file(candidate, tried)? {
+            return Ok(Some(path));
+        }
+
+        self.try_directory(candidate, tried)
+    }
+
+    /// Try `candidate` itself, then `candidate` with each configured extension
+    fn try_file(&self, candidate: &Path, tried: &mut Vec<Candidate>) -> Result<Option<PathBuf>> {
+        if let Some(path) = self.probe(candidate.to_path_buf(), CandidateKind::Exact, tried)? {
+            return Ok(Some(path));
+        }
+
+        for ext in &self.extensions {
+            // Append rather than replace, so `./app.config` can find `app.config.js`
+            let mut with_ext = candidate.as_os_str().to_owned();
+            with_ext.push(ext);
+
+            if let Some(path) =
+                self.probe(PathBuf::from(with_ext), CandidateKind::Extension, tried)?
+            {
+                return Ok(Some(path));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Try the index file of `candidate` as a directory
+    fn try_directory(
+        &self,
+        candidate: &Path,
+        tried: &mut Vec<Candidate>,
+    ) -> Result<Option<PathBuf>> {
+        if !fs::is_dir(candidate) {
+            return Ok(None);
+        }
+
+        for ext in &self.extensions {
+            let index_file = candidate.join(format!("index{}", ext));
+            if let Some(path) = self.probe(index_file, CandidateKind::DirectoryIndex, tried)? {
+                return Ok(Some(path));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Record `path` as tried, or return it according to the symlink policy
+    /// if it is a file
+    fn probe(
+        &self,
+        path: PathBuf,
+        kind: CandidateKind,
+        tried: &mut Vec<Candidate>,
+    ) -> Result<Option<PathBuf>> {
+        if !fs::is_file(&path) {
+            tried.push(Candidate { path, kind });
+            return Ok(None);
+        }
+
+        let real_path = fs::canonicalize(&path)?;
+        if self.symlinks {
+            return Ok(Some(real_path));
+        }
+
+        let link_path = path.absolutize()?.to_path_buf();
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(Some(seen.entry(real_path).or_insert(link_path).clone()))
+    }
+
+    /// The Plug'n'Play manifest covering `dir`, if any and if enabled
+    fn pnp_manifest(&self, dir: &Path) -> Result<Option<Arc<PnpManifest>>> {
+        if !self.pnp {
+            return Ok(None);
+        }
+        let Some(path) = PnpManifest::find(dir) else {
+            return Ok(None);
+        };
+
+        let mut manifests = self.pnp_manifests.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(manifest) = manifests.get(&path) {
+            return Ok(Some(manifest.clone()));
+        }
+
+        let manifest = Arc::new(PnpManifest::load(&path)?);
+        manifests.insert(path, manifest.clone());
+        Ok(Some(manifest))
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether `request` names a virtual module. `virtual:` is the convention
+/// Vite plugins use; a leading NUL byte is Rollup's marker for ids that must
+/// not be touched by other resolvers.
+pub fn is_virtual(request: &str) -> bool {
+    request.starts_with("virtual:") || request.starts_with('\0')
+}
+
+/// A request split into the path to resolve and the parts that only matter
+/// to loaders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParsedRequest<'a> {
+    pub path: &'a str,
+    /// Resource query including the leading `?`, e.g. `?raw`
+    pub query: Option<&'a str>,
+    /// Fragment including the leading `#`, e.g. `#section`
+    pub fragment: Option<&'a str>,
+}
+
+/// Split `./icon.svg?raw#top` into `./icon.svg`, `?raw` and `#top`, the same
+/// way webpack splits a request into resource, resource query and fragment.
+/// The query is split off first, so `a#b.js?x` names the file `a#b.js`.
+/// Requests starting with `#` are subpath imports and have no fragment.
+pub fn parse_request<'a>(request: &'a str) -> ParsedRequest<'a> {
+    let subpath_import = request.starts_with('#');
+    let split_fragment = |rest: &'a str| -> (&'a str, Option<&'a str>) {
+        match rest.find('#') {
+            Some(index) if !subpath_import => (&rest[..index], Some(&rest[index..])),
+            _ => (rest, None),
+        }
+    };
+    let (path, query, fragment) = match request.find('?') {
+        Some(index) => {
+            let (query, fragment) = split_fragment(&request[index..]);
+            (&request[..index], Some(query), fragment)
+        }
+        None => {
+            let (path, fragment) = split_fragment(request);
+            (path, None, fragment)
+        }
+    };
+
+    ParsedRequest {
+        path,
+        query,
+        fragment,
+    }
+}
+
+fn context_dir(context: &Path) -> &Path {
+    if fs::is_file(context) {
+        context.parent().unwrap_or(context)
+    } else {
+        context
+    }
+}
+
+/// Split a bare request into its package name and the path inside the
+/// package, e.g. `@scope/pkg/lib/a` into `@scope/pkg` and `lib/a`
+fn split_package_request(request: &str) -> (&str, Option<&str>) {
+    let name_end = if request.starts_with('@') {
+        request
+            .match_indices('/')
+            .nth(1)
+            .map_or(request.len(), |(index, _)| index)
+    } else {
+        request.find('/').unwrap_or(request.len())
+    };
+
+    let (name, rest) = request.split_at(name_end);
+    let subpath = rest.strip_prefix('/').filter(|subpath| !subpath.is_empty());
+    (name, subpath)
 }
 
 #[cfg(test)]
@@ -104,7 +713,7 @@ mod tests {
         let main_path = base_path.join("main.js");
 
         let result = resolver.resolve("./helper.js", &main_path).await.unwrap();
-        assert!(result.ends_with("helper.js"));
+        assert!(result.path().unwrap().ends_with("helper.js"));
     }
 
     #[tokio::test]
@@ -119,6 +728,301 @@ mod tests {
         let main_path = base_path.join("main.js");
 
         let result = resolver.resolve("./helper", &main_path).await.unwrap();
-        assert!(result.ends_with("helper.ts"));
+        assert!(result.path().unwrap().ends_with("helper.ts"));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_ignores_query_and_fragment() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path();
+
+        fs::write(base_path.join("main.js"), "").unwrap();
+        fs::write(base_path.join("icon.svg"), "").unwrap();
+
+        let resolver = Resolver::new();
+        let main_path = base_path.join("main.js");
+
+        let result = resolver
+            .resolve("./icon.svg?raw#top", &main_path)
+            .await
+            .unwrap();
+        assert!(result.path().unwrap().ends_with("icon.svg"));
+
+        let parsed = parse_request("./icon.svg?raw#top");
+        assert_eq!(parsed.path, "./icon.svg");
+        assert_eq!(parsed.query, Some("?raw"));
+        assert_eq!(parsed.fragment, Some("#top"));
+
+        let parsed = parse_request("#internal/foo");
+        assert_eq!(parsed.path, "#internal/foo");
+        assert_eq!(parsed.fragment, None);
+
+        let parsed = parse_request("a#b.js?x");
+        assert_eq!(parsed.path, "a#b.js");
+        assert_eq!(parsed.query, Some("?x"));
+        assert_eq!(parsed.fragment, None);
+
+        let parsed = parse_request("./icon.svg#top");
+        assert_eq!(parsed.path, "./icon.svg");
+        assert_eq!(parsed.fragment, Some("#top"));
+    }
+
+    #[tokio::test]
+    async fn test_not_found_lists_candidates_and_suggestions() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path();
+
+        fs::write(base_path.join("main.js"), "").unwrap();
+        fs::write(base_path.join("utils.js"), "").unwrap();
+
+        let resolver = Resolver::new();
+        let main_path = base_path.join("main.js");
+
+        let err = resolver.resolve("./utlis", &main_path).await.unwrap_err();
+        let ResolverError::ModuleNotFound {
+            tried, suggestions, ..
+        } = &err
+        else {
+            panic!("unexpected error: {err}");
+        };
+
+        assert_eq!(tried.len(), 1 + resolver.extensions.len());
+        assert_eq!(tried[0].kind, CandidateKind::Exact);
+        assert!(tried[1].path.ends_with("utlis.js"));
+        assert_eq!(suggestions, &["./utils.js".to_string()]);
+        assert!(err.to_string().contains("Did you mean:"));
+
+        // `./bc` is two edits away from `a.js`, but too short for that to
+        // mean anything
+        fs::write(base_path.join("a.js"), "").unwrap();
+        let err = resolver.resolve("./bc", &main_path).await.unwrap_err();
+        let ResolverError::ModuleNotFound { suggestions, .. } = &err else {
+            panic!("unexpected error: {err}");
+        };
+        assert!(suggestions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_package_exports() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path();
+        let package = base_path.join("node_modules/pkg");
+        fs::create_dir_all(package.join("browser")).unwrap();
+        fs::write(
+            package.join("package.json"),
+            r#"{
+                "main": "./main.js",
+                "exports": {
+                    ".": { "require": "./index.cjs", "import": "./index.mjs", "default": "./main.js" },
+                    "./features/*": { "browser": "./browser/*.js", "default": "./node/*.js" },
+                    "./private/*": null
+                }
+            }"#,
+        )
+        .unwrap();
+        fs::write(package.join("main.js"), "").unwrap();
+        fs::write(package.join("browser/a.js"), "").unwrap();
+        fs::write(base_path.join("main.js"), "").unwrap();
+        let main_path = base_path.join("main.js");
+
+        // `index.mjs` doesn't exist, so `default` is next; `require` never matches
+        let resolver = Resolver::new();
+        let resolved = resolver.resolve("pkg", &main_path).await.unwrap();
+        assert_eq!(
+            resolved.path().unwrap(),
+            package.join("main.js").canonicalize().unwrap()
+        );
+
+        let resolver = Resolver::new().with_conditions(vec!["browser".to_string()]);
+        let resolved = resolver
+            .resolve("pkg/features/a", &main_path)
+            .await
+            .unwrap();
+        assert_eq!(
+            resolved.path().unwrap(),
+            package.join("browser/a.js").canonicalize().unwrap()
+        );
+
+        // Every target the conditions picked is listed
+        let err = Resolver::new()
+            .resolve("pkg/features/b", &main_path)
+            .await
+            .unwrap_err();
+        let ResolverError::ModuleNotFound { tried, .. } = &err else {
+            panic!("unexpected error: {err}");
+        };
+        let exports: Vec<_> = tried
+            .iter()
+            .filter(|candidate| candidate.kind == CandidateKind::PackageExports)
+            .map(|candidate| candidate.path.clone())
+            .collect();
+        assert_eq!(exports, [package.join("node/b.js")]);
+        assert!(err.to_string().contains("(package.json exports)"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pnpm_layout_without_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path();
+        let store = base_path.join("node_modules/.pnpm");
+
+        // react@18 and a plugin depending on it as a peer, laid out like pnpm
+        let react = store.join("react@18.0.0/node_modules/react");
+        let plugin_deps = store.join("plugin@1.0.0_react@18.0.0/node_modules");
+        fs::create_dir_all(&react).unwrap();
+        fs::create_dir_all(plugin_deps.join("plugin")).unwrap();
+        fs::write(react.join("index.js"), "").unwrap();
+        fs::write(plugin_deps.join("plugin/index.js"), "").unwrap();
+        symlink(&react, plugin_deps.join("react")).unwrap();
+        symlink(&react, base_path.join("node_modules/react")).unwrap();
+        symlink(
+            plugin_deps.join("plugin"),
+            base_path.join("node_modules/plugin"),
+        )
+        .unwrap();
+        fs::write(base_path.join("main.js"), "").unwrap();
+
+        let resolver = Resolver::new().with_symlinks(false);
+        let main_path = base_path.join("main.js");
+
+        let plugin = resolver.resolve("plugin", &main_path).await.unwrap();
+        let plugin = plugin.path().unwrap();
+        assert_eq!(plugin, base_path.join("node_modules/plugin/index.js"));
+
+        // The peer is found next to the plugin's real location, and is the
+        // same module the app gets when importing it directly
+        let react_from_plugin = resolver.resolve("react", plugin).await.unwrap();
+        let react_from_app = resolver.resolve("react", &main_path).await.unwrap();
+        assert_eq!(react_from_plugin, react_from_app);
+        assert!(react_from_plugin.path().unwrap().starts_with(&store));
+    }
+
+    #[tokio::test]
+    async fn test_pnp_resolves_into_zip_archive() {
+        use std::io::Write;
+
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path();
+        let cache = base_path.join(".yarn/cache");
+        fs::create_dir_all(&cache).unwrap();
+
+        let archive = cache.join("lodash-npm-4.17.21-abc.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("node_modules/lodash/package.json", options)
+            .unwrap();
+        zip.write_all(br#"{"main": "lodash.js"}"#).unwrap();
+        zip.start_file("node_modules/lodash/lodash.js", options)
+            .unwrap();
+        zip.write_all(b"module.exports = {};").unwrap();
+        zip.finish().unwrap();
+
+        fs::write(
+            base_path.join(".pnp.data.json"),
+            r#"{
+                "enableTopLevelFallback": false,
+                "fallbackPool": [],
+                "packageRegistryData": [
+                    [null, [[null, {
+                        "packageLocation": "./",
+                        "packageDependencies": [["lodash", "npm:4.17.21"]]
+                    }]]],
+                    ["lodash", [["npm:4.17.21", {
+                        "packageLocation": "./.yarn/cache/lodash-npm-4.17.21-abc.zip/node_modules/lodash/",
+                        "packageDependencies": [["lodash", "npm:4.17.21"]]
+                    }]]]
+                ]
+            }"#,
+        )
+        .unwrap();
+        fs::write(base_path.join("main.js"), "").unwrap();
+
+        let resolver = Resolver::new();
+        let main_path = base_path.join("main.js");
+
+        let result = resolver.resolve("lodash", &main_path).await.unwrap();
+        let result = result.path().unwrap();
+        assert!(result.ends_with("lodash-npm-4.17.21-abc.zip/node_modules/lodash/lodash.js"));
+        assert_eq!(crate::fs::read(result).unwrap(), b"module.exports = {};");
+
+        let err = resolver.resolve("react", &main_path).await.unwrap_err();
+        assert!(matches!(err, ResolverError::UndeclaredDependency { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_node_builtins() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path();
+
+        fs::write(base_path.join("main.js"), "").unwrap();
+        fs::create_dir_all(base_path.join("node_modules/path-browserify")).unwrap();
+        fs::write(base_path.join("node_modules/path-browserify/index.js"), "").unwrap();
+        let main_path = base_path.join("main.js");
+
+        let node = Resolver::new().with_externalize_builtins(true);
+        let result = node.resolve("node:fs", &main_path).await.unwrap();
+        assert_eq!(result, Resolution::Builtin("node:fs".to_string()));
+
+        let web = Resolver::new().with_fallback(HashMap::from([
+            (
+                "path".to_string(),
+                Fallback::Module("path-browserify".to_string()),
+            ),
+            ("fs".to_string(), Fallback::Empty),
+        ]));
+        let result = web.resolve("node:path", &main_path).await.unwrap();
+        assert!(result.path().unwrap().ends_with("path-browserify/index.js"));
+        assert_eq!(
+            web.resolve("fs", &main_path).await.unwrap(),
+            Resolution::Empty
+        );
+
+        let err = web.resolve("crypto", &main_path).await.unwrap_err();
+        assert!(matches!(err, ResolverError::NodeBuiltin { .. }));
+    }
+
+    #[test]
+    fn test_resolve_sync_with_alias_and_trace() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path();
+
+        fs::create_dir_all(base_path.join("src/components")).unwrap();
+        fs::write(base_path.join("src/components/button.ts"), "").unwrap();
+        fs::write(base_path.join("main.js"), "").unwrap();
+
+        let components = base_path.join("src/components");
+        let resolver = Resolver::new().with_alias(HashMap::from([(
+            "@components".to_string(),
+            components.display().to_string(),
+        )]));
+        let main_path = base_path.join("main.js");
+
+        let (resolution, trace) = resolver
+            .resolve_traced("@components/button", &main_path)
+            .unwrap();
+        assert!(
+            resolution
+                .path()
+                .unwrap()
+                .ends_with("src/components/button.ts")
+        );
+        assert_eq!(trace.len(), 2);
+        assert!(trace[1].path.ends_with("button.js"));
+
+        assert!(resolver.resolve_sync("@components", &main_path).is_err());
+    }
+
+    #[test]
+    fn test_virtual_modules() {
+        let resolver = Resolver::new();
+        let context = Path::new("main.js");
+
+        for request in ["virtual:routes", "\0build-info"] {
+            let resolution = resolver.resolve_sync(request, context).unwrap();
+            assert_eq!(resolution, Resolution::Virtual(request.to_string()));
+        }
     }
 }
@@ -0,0 +1,244 @@
+//! Yarn Plug'n'Play manifest support
+//!
+//! Projects installed with `nodeLinker: pnp` have no `node_modules` tree.
+//! Instead Yarn writes a manifest (`.pnp.data.json`, or inlined in `.pnp.cjs`)
+//! mapping every package to its location and to the exact packages each of
+//! its dependencies refers to. See
+//! <https://yarnpkg.com/advanced/pnp-spec> for the format.
+
+use crate::{ResolverError, Result};
+use path_absolutize::Absolutize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const DATA_FILE: &str = ".pnp.data.json";
+const RUNTIME_FILE: &str = ".pnp.cjs";
+
+/// A package name and reference. The top-level project is `(None, None)`.
+type Locator = (Option<String>, Option<String>);
+
+pub(crate) struct PnpManifest {
+    path: PathBuf,
+    packages: HashMap<Locator, PackageInfo>,
+    /// Package locations sorted longest first, for issuer lookups
+    locations: Vec<(PathBuf, Locator)>,
+    enable_top_level_fallback: bool,
+    fallback_pool: HashMap<String, Option<Locator>>,
+}
+
+struct PackageInfo {
+    location: PathBuf,
+    /// `None` marks a peer dependency the parent doesn't provide
+    dependencies: HashMap<String, Option<Locator>>,
+}
+
+/// Outcome of mapping a package name to a directory
+pub(crate) enum PnpResolution {
+    /// The package's directory, which may be inside a zip archive
+    Resolved(PathBuf),
+    /// The issuer isn't part of this manifest; use regular resolution
+    NotOwned,
+}
+
+impl PnpManifest {
+    /// Find the manifest governing `dir`, looking in `dir` and its ancestors
+    pub(crate) fn find(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors().find_map(|ancestor| {
+            [DATA_FILE, RUNTIME_FILE]
+                .iter()
+                .map(|name| ancestor.join(name))
+                .find(|path| path.is_file())
+        })
+    }
+
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let json = if path.ends_with(RUNTIME_FILE) {
+            extract_runtime_state(&content).ok_or_else(|| invalid(path, "no RAW_RUNTIME_STATE"))?
+        } else {
+            content
+        };
+        let data: Value = serde_json::from_str(&json).map_err(|e| invalid(path, &e.to_string()))?;
+
+        let root = path.parent().unwrap_or(Path::new(".")).canonicalize()?;
+        let mut packages = HashMap::new();
+
+        let registry = data["packageRegistryData"]
+            .as_array()
+            .ok_or_else(|| invalid(path, "missing packageRegistryData"))?;
+        for entry in registry {
+            let name = entry[0].as_str().map(str::to_string);
+            for store in entry[1].as_array().into_iter().flatten() {
+                let reference = store[0].as_str().map(str::to_string);
+                let info = &store[1];
+
+                let location = info["packageLocation"]
+                    .as_str()
+                    .ok_or_else(|| invalid(path, "package without packageLocation"))?;
+                let location = root.join(location).absolutize()?.to_path_buf();
+
+                let dependencies = info["packageDependencies"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|dependency| {
+                        let dependency_name = dependency[0].as_str()?.to_string();
+                        let target = parse_target(&dependency_name, &dependency[1]);
+                        Some((dependency_name, target))
+                    })
+                    .collect();
+
+                packages.insert(
+                    (name.clone(), reference),
+                    PackageInfo {
+                        location,
+                        dependencies,
+                    },
+                );
+            }
+        }
+
+        let fallback_pool = data["fallbackPool"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|dependency| {
+                let dependency_name = dependency[0].as_str()?.to_string();
+                let target = parse_target(&dependency_name, &dependency[1]);
+                Some((dependency_name, target))
+            })
+            .collect();
+
+        let mut locations: Vec<(PathBuf, Locator)> = packages
+            .iter()
+            .map(|(locator, info)| (info.location.clone(), locator.clone()))
+            .collect();
+        locations.sort_by_key(|(location, _)| std::cmp::Reverse(location.as_os_str().len()));
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            packages,
+            locations,
+            enable_top_level_fallback: data["enableTopLevelFallback"].as_bool().unwrap_or(false),
+            fallback_pool,
+        })
+    }
+
+    /// Map `package_name`, imported from `issuer`, to the directory of the
+    /// package the issuer depends on
+    pub(crate) fn resolve_package(
+        &self,
+        package_name: &str,
+        issuer: &Path,
+    ) -> Result<PnpResolution> {
+        let Some(issuer_locator) = self.find_locator(issuer) else {
+            return Ok(PnpResolution::NotOwned);
+        };
+        let issuer_info = &self.packages[issuer_locator];
+
+        let target = match issuer_info.dependencies.get(package_name) {
+            Some(target) => target.clone(),
+            None if self.enable_top_level_fallback => self
+                .fallback_pool
+                .get(package_name)
+                .cloned()
+                .ok_or_else(|| self.undeclared(package_name, issuer_locator, issuer))?,
+            None => return Err(self.undeclared(package_name, issuer_locator, issuer)),
+        };
+
+        let Some(locator) = target else {
+            return Err(ResolverError::UndeclaredDependency {
+                request: package_name.to_string(),
+                issuer: describe(issuer_locator),
+                context: issuer.display().to_string(),
+                reason: "it is a peer dependency its parent does not provide".to_string(),
+            });
+        };
+
+        let info = self.packages.get(&locator).ok_or_else(|| {
+            invalid(
+                &self.path,
+                &format!("dependency {} has no registry entry", describe(&locator)),
+            )
+        })?;
+        Ok(PnpResolution::Resolved(info.location.clone()))
+    }
+
+    fn find_locator(&self, path: &Path) -> Option<&Locator> {
+        self.locations
+            .iter()
+            .find(|(location, _)| path.starts_with(location))
+            .map(|(_, locator)| locator)
+    }
+
+    fn undeclared(
+        &self,
+        package_name: &str,
+        issuer_locator: &Locator,
+        issuer: &Path,
+    ) -> ResolverError {
+        ResolverError::UndeclaredDependency {
+            request: package_name.to_string(),
+            issuer: describe(issuer_locator),
+            context: issuer.display().to_string(),
+            reason: "it is not listed in its dependencies".to_string(),
+        }
+    }
+}
+
+/// A dependency value is a reference of a package with the same name, an
+/// alias `[name, reference]`, or `null` for a missing peer
+fn parse_target(dependency_name: &str, value: &Value) -> Option<Locator> {
+    match value {
+        Value::String(reference) => {
+            Some((Some(dependency_name.to_string()), Some(reference.clone())))
+        }
+        Value::Array(alias) => Some((
+            alias.first()?.as_str().map(str::to_string),
+            alias.get(1)?.as_str().map(str::to_string),
+        )),
+        _ => None,
+    }
+}
+
+fn describe(locator: &Locator) -> String {
+    match locator {
+        (Some(name), Some(reference)) => format!("{}@{}", name, reference),
+        (Some(name), None) => name.clone(),
+        _ => "the top-level project".to_string(),
+    }
+}
+
+fn invalid(path: &Path, message: &str) -> ResolverError {
+    ResolverError::InvalidPnpManifest {
+        path: path.display().to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Pull the JSON out of `const RAW_RUNTIME_STATE = '...';` in `.pnp.cjs`
+fn extract_runtime_state(source: &str) -> Option<String> {
+    let start = source.find("RAW_RUNTIME_STATE")?;
+    let rest = source[start..].split_once('=')?.1.trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+
+    let mut json = String::new();
+    let mut chars = rest[1..].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                // Line continuation
+                '\n' => {}
+                'n' => json.push('\n'),
+                't' => json.push('\t'),
+                'r' => json.push('\r'),
+                other => json.push(other),
+            },
+            c if c == quote => return Some(json),
+            c => json.push(c),
+        }
+    }
+
+    None
+}
@@ -0,0 +1,91 @@
+//! Near-miss suggestions for requests that could not be resolved
+
+use std::path::Path;
+
+/// Maximum edit distance for a file name to count as a typo of the request
+const MAX_TYPO_DISTANCE: usize = 2;
+
+/// Maximum number of suggestions attached to a single error
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Look for files next to the requested path whose names are close to it:
+/// the same name in a different case, the same stem with another extension,
+/// or a name within a small edit distance.
+///
+/// Suggestions are returned as specifiers in the same form as `request`, so
+/// `./utlis` yields `./utils.js` rather than an absolute path.
+pub(crate) fn suggest(request: &str, context_dir: &Path) -> Vec<String> {
+    let (prefix, wanted) = match request.rfind('/') {
+        Some(index) => request.split_at(index + 1),
+        None => ("", request),
+    };
+
+    if wanted.is_empty() || wanted == "." || wanted == ".." {
+        return Vec::new();
+    }
+
+    let dir = context_dir.join(prefix);
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+
+    let wanted_stem = stem(wanted);
+    // Very short names are within a couple of edits of almost anything
+    let max_distance = MAX_TYPO_DISTANCE.min(wanted_stem.chars().count() / 2);
+    let mut scored: Vec<(usize, String)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name != wanted)
+        .filter_map(|name| {
+            let score = if name.eq_ignore_ascii_case(wanted) {
+                // Wrong case, e.g. `./Helper.js` for `helper.js`
+                0
+            } else if stem(&name) == wanted_stem {
+                // Wrong or missing extension, e.g. `./helper.ts` for `helper.js`
+                1
+            } else {
+                let distance = levenshtein(&name.to_lowercase(), &wanted.to_lowercase()).min(
+                    levenshtein(&stem(&name).to_lowercase(), &wanted_stem.to_lowercase()),
+                );
+                if distance > max_distance {
+                    return None;
+                }
+                1 + distance
+            };
+            Some((score, name))
+        })
+        .collect();
+
+    scored.sort();
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| format!("{}{}", prefix, name))
+        .collect()
+}
+
+fn stem(name: &str) -> &str {
+    match name.rfind('.') {
+        Some(0) | None => name,
+        Some(index) => &name[..index],
+    }
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current[j + 1] = (previous[j] + cost)
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
@@ -10,23 +10,23 @@ export default defineConfig({
   module: {
     rules: [
       {
-        test: "\\.js$",
-        useLoader: "javascript",
+        test: "\\.jsx?$",
+        use: "javascript",
         exclude: "node_modules"
       },
       {
-        test: "\\.ts$",
-        useLoader: "typescript", 
+        test: "\\.tsx?$",
+        use: "typescript", 
         exclude: "node_modules"
       },
       {
         test: "\\.json$",
-        useLoader: "json"
+        use: "json"
       }
     ]
   },
   resolve: {
-    extensions: [".js", ".ts", ".json"],
+    extensions: [".js", ".jsx", ".ts", ".tsx", ".json"],
     alias: {}
   },
   plugins: [],